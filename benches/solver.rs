@@ -0,0 +1,66 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use logic_solver::interpreter::evaluate_ref;
+use logic_solver::lexer::Lexer;
+use logic_solver::parser::construct_ast;
+use logic_solver::table::{truth_table, truth_table_fast};
+use logic_solver::{parse_expr, stored_variables};
+
+const VARIABLES: [&str; 10] = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+
+/// Assigns every variable and then joins a few hundred clauses over them
+fn large_formula() -> String {
+    let mut formula: String = VARIABLES
+        .iter()
+        .enumerate()
+        .map(|(i, var)| format!("{} := {}\n", var, i % 2))
+        .collect();
+    let clauses: Vec<String> = (0..500)
+        .map(|i| {
+            let var = |offset: usize| VARIABLES[(i + offset) % VARIABLES.len()];
+            format!("({} => ~{} v {} + {})", var(0), var(3), var(5), var(7))
+        })
+        .collect();
+    formula.push_str(&clauses.join(" ^ "));
+    formula
+}
+
+fn bench_construct_ast(c: &mut Criterion) {
+    let formula = large_formula();
+    c.bench_function("construct_ast 500 clauses", |b| {
+        b.iter(|| construct_ast(&mut Lexer::new(black_box(&formula))).unwrap())
+    });
+}
+
+fn bench_evaluate(c: &mut Criterion) {
+    // A chain of conjunctions is as deep as it is long
+    let formula = vec!["p"; 900].join(" ^ ");
+    let (root, _) = parse_expr(&formula).unwrap();
+    let vars = stored_variables([("p", true)]);
+    c.bench_function("evaluate depth 900", |b| {
+        b.iter(|| evaluate_ref(black_box(&root), &vars).unwrap())
+    });
+}
+
+fn bench_truth_table(c: &mut Criterion) {
+    let formula = VARIABLES
+        .chunks(2)
+        .map(|pair| format!("({} + {})", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join(" => ");
+    let (root, _) = parse_expr(&formula).unwrap();
+    c.bench_function("truth_table n=10", |b| {
+        b.iter(|| truth_table(black_box(&root)).unwrap())
+    });
+    c.bench_function("truth_table_fast n=10", |b| {
+        b.iter(|| truth_table_fast(black_box(&root)).unwrap())
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_construct_ast,
+    bench_evaluate,
+    bench_truth_table
+);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::parser::ASTNode;
+
+/// Formula in the DIMACS CNF format read by SAT solvers.
+/// Variables are numbered from 1 in alphabetical order, and the numbering is listed
+/// in `c` comment lines. Constants have no DIMACS literal, so they are folded while
+/// building the clauses: a true constant drops its clause and a false one drops itself
+/// from its clause. A formula that is always true thus has no clauses, and one
+/// that is always false has an empty clause, written as a lone `0`.
+pub fn to_dimacs(node: &ASTNode) -> Result<String> {
+    let variables = node.free_variables();
+    let numbers: HashMap<&String, usize> = variables
+        .iter()
+        .enumerate()
+        .map(|(i, var)| (var, i + 1))
+        .collect();
+    let clauses = node.cnf_clauses()?;
+
+    let mut dimacs = String::new();
+    for (var, number) in variables.iter().zip(1..) {
+        dimacs.push_str(&format!("c {} {}\n", number, var));
+    }
+    dimacs.push_str(&format!("p cnf {} {}\n", variables.len(), clauses.len()));
+    for clause in clauses {
+        for literal in clause {
+            let sign = if literal.negated { "-" } else { "" };
+            dimacs.push_str(&format!("{}{} ", sign, numbers[&literal.var]));
+        }
+        dimacs.push_str("0\n");
+    }
+    Ok(dimacs)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::to_dimacs;
+
+    #[test]
+    fn test_to_dimacs() {
+        let dimacs = to_dimacs(&parse("(p ^ ~q) v r")).unwrap();
+
+        assert_eq!(dimacs, "c 1 p\nc 2 q\nc 3 r\np cnf 3 2\n1 3 0\n-2 3 0\n");
+    }
+
+    #[test]
+    fn test_to_dimacs_counts_match_clauses() {
+        let root = parse("(p <=> q) ^ (r => ~p) ^ (s v q v 0)");
+
+        let dimacs = to_dimacs(&root).unwrap();
+
+        let header: Vec<&str> = dimacs
+            .lines()
+            .find(|line| line.starts_with("p cnf"))
+            .unwrap()
+            .split_whitespace()
+            .collect();
+        let clause_lines: Vec<&str> = dimacs
+            .lines()
+            .filter(|line| !line.starts_with('c') && !line.starts_with('p'))
+            .collect();
+        assert_eq!(header[2], root.free_variables().len().to_string());
+        assert_eq!(header[3], clause_lines.len().to_string());
+        assert_eq!(clause_lines.len(), root.cnf_clauses().unwrap().len());
+        assert!(clause_lines.iter().all(|line| line.ends_with(" 0")));
+    }
+
+    #[test]
+    fn test_to_dimacs_constants() {
+        assert_eq!(to_dimacs(&parse("1")).unwrap(), "p cnf 0 0\n");
+        assert_eq!(
+            to_dimacs(&parse("p ^ 0")).unwrap(),
+            "c 1 p\np cnf 1 2\n1 0\n0\n"
+        );
+    }
+}
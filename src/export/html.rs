@@ -0,0 +1,189 @@
+use anyhow::Result;
+
+use crate::lexer::Token;
+use crate::parser::ASTNode;
+use crate::table::truth_table_over;
+
+const NODE_SPACING: usize = 60;
+const LEVEL_SPACING: usize = 70;
+
+struct PlacedNode {
+    x: usize,
+    y: usize,
+    label: String,
+    operator: bool,
+}
+
+/// Places the nodes in the order of in-order traversal horizontally and by depth vertically,
+/// so no two subtrees overlap
+fn place(
+    node: &ASTNode,
+    depth: usize,
+    column: &mut usize,
+    nodes: &mut Vec<PlacedNode>,
+    edges: &mut Vec<(usize, usize)>,
+) -> usize {
+    let left = node
+        .left
+        .as_ref()
+        .map(|left| place(left, depth + 1, column, nodes, edges));
+    let index = nodes.len();
+    nodes.push(PlacedNode {
+        x: *column * NODE_SPACING + NODE_SPACING / 2,
+        y: depth * LEVEL_SPACING + LEVEL_SPACING / 2,
+        label: node.token.to_string(),
+        operator: matches!(node.token, Token::Operator(_)),
+    });
+    *column += 1;
+    let right = node
+        .right
+        .as_ref()
+        .map(|right| place(right, depth + 1, column, nodes, edges));
+    for child in [left, right].into_iter().flatten() {
+        edges.push((index, child));
+    }
+    index
+}
+
+/// Draws the tree as inline SVG, operators as boxes and values as ellipses like in the graphviz output
+fn ast_svg(root: &ASTNode) -> String {
+    let mut nodes = vec![];
+    let mut edges = vec![];
+    let mut column = 0;
+    place(root, 0, &mut column, &mut nodes, &mut edges);
+
+    let width = column * NODE_SPACING;
+    let height = nodes.iter().map(|n| n.y).max().unwrap_or(0) + LEVEL_SPACING / 2;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        width, height
+    );
+    for (parent, child) in edges {
+        svg.push_str(&format!(
+            "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>\n",
+            nodes[parent].x, nodes[parent].y, nodes[child].x, nodes[child].y
+        ));
+    }
+    for node in &nodes {
+        let shape = if node.operator {
+            format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"50\" height=\"26\" fill=\"white\" stroke=\"black\"/>",
+                node.x - 25,
+                node.y - 13
+            )
+        } else {
+            format!(
+                "<ellipse cx=\"{}\" cy=\"{}\" rx=\"25\" ry=\"13\" fill=\"white\" stroke=\"black\"/>",
+                node.x, node.y
+            )
+        };
+        svg.push_str(&format!(
+            "  {}\n  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" dominant-baseline=\"middle\" font-size=\"12\">{}</text>\n",
+            shape,
+            node.x,
+            node.y,
+            escape(&node.label)
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn bit(value: bool) -> &'static str {
+    if value {
+        "1"
+    } else {
+        "0"
+    }
+}
+
+/// Self-contained page with the formula, its truth table over `vars`
+/// and a drawing of the AST, needing nothing external to be viewed
+pub fn to_html(root: &ASTNode, vars: &[String]) -> Result<String> {
+    let expression = escape(&root.to_expr_string());
+    let table = truth_table_over(root, vars)?;
+
+    let mut rows = String::new();
+    rows.push_str("    <tr>");
+    for var in &table.variables {
+        rows.push_str(&format!("<th>{}</th>", escape(var)));
+    }
+    rows.push_str("<th>result</th></tr>\n");
+    for row in &table.rows {
+        rows.push_str("    <tr>");
+        for (_, value) in &row.assignment {
+            rows.push_str(&format!("<td>{}</td>", bit(*value)));
+        }
+        rows.push_str(&format!("<td>{}</td></tr>\n", bit(row.result)));
+    }
+
+    Ok(format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+  <meta charset=\"utf-8\">
+  <title>{expression}</title>
+  <style>
+    body {{ font-family: sans-serif; }}
+    table {{ border-collapse: collapse; }}
+    th, td {{ border: 1px solid #999; padding: 2px 8px; text-align: center; }}
+  </style>
+</head>
+<body>
+  <h1><code>{expression}</code></h1>
+  <h2>Truth table</h2>
+  <table>
+{rows}  </table>
+  <h2>Syntax tree</h2>
+  <div class=\"graph\">
+{svg}
+  </div>
+</body>
+</html>
+",
+        expression = expression,
+        rows = rows,
+        svg = ast_svg(root),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::to_html;
+
+    #[test]
+    fn test_to_html_contains_sections() {
+        let root = parse("p ^ (q v r)");
+        let vars = vec!["p".to_string(), "q".to_string(), "r".to_string()];
+
+        let html = to_html(&root, &vars).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<code>p ^ (q v r)</code>"));
+        assert!(html.contains("<table>"));
+        assert_eq!(html.matches("<tr>").count(), 9);
+        assert!(html.contains("<svg"));
+        assert_eq!(html.matches("<rect").count(), 2);
+        assert_eq!(html.matches("<ellipse").count(), 3);
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn test_to_html_escapes_operators() {
+        let root = parse("p <=> q");
+        let vars = vec!["p".to_string(), "q".to_string()];
+
+        let html = to_html(&root, &vars).unwrap();
+
+        assert!(html.contains("<code>p &lt;=&gt; q</code>"));
+    }
+}
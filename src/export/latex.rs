@@ -0,0 +1,92 @@
+use crate::lexer::{Operator, Token, Value};
+use crate::parser::ASTNode;
+
+fn latex_symbol(op: &Operator) -> &'static str {
+    match op {
+        Operator::Equivalence => "\\leftrightarrow",
+        Operator::Implication => "\\rightarrow",
+        Operator::Or => "\\lor",
+        Operator::Nor => "\\downarrow",
+        Operator::Xor => "\\oplus",
+        Operator::And => "\\land",
+        Operator::Nand => "\\uparrow",
+        Operator::Not => "\\neg",
+        other => other.symbol(),
+    }
+}
+
+/// Operand parenthesized by the same rule as in `ASTNode::to_expr_string`: when it binds
+/// looser than its parent, or as loose on the side the parent doesn't group to
+fn operand(node: &Option<Box<ASTNode>>, parent: &Operator, is_right: bool) -> String {
+    let groups_to_operand = parent.is_right_associative() == is_right;
+    match node {
+        Some(node) => match &node.token {
+            Token::Operator(op)
+                if *op != Operator::Not
+                    && (op < parent || (op == parent && !groups_to_operand)) =>
+            {
+                format!("({})", to_latex(node))
+            }
+            _ => to_latex(node),
+        },
+        None => String::new(),
+    }
+}
+
+/// Formula in LaTeX math notation, parenthesized the same way as `ASTNode::to_expr_string`.
+/// Constants are written as `\top` and `\bot`.
+pub fn to_latex(node: &ASTNode) -> String {
+    match &node.token {
+        Token::Value(Value::Bool(true)) => "\\top".to_string(),
+        Token::Value(Value::Bool(false)) => "\\bot".to_string(),
+        Token::Value(Value::Variable(name)) => name.clone(),
+        Token::Operator(Operator::Not) => {
+            format!("\\neg {}", operand(&node.left, &Operator::Not, false))
+        }
+        Token::Operator(op) => format!(
+            "{} {} {}",
+            operand(&node.left, op, false),
+            latex_symbol(op),
+            operand(&node.right, op, true)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::to_latex;
+
+    #[test]
+    fn test_to_latex_negation() {
+        assert_eq!(to_latex(&parse("~(p ^ q)")), "\\neg (p \\land q)");
+        assert_eq!(to_latex(&parse("~~p")), "\\neg \\neg p");
+    }
+
+    #[test]
+    fn test_to_latex_operators() {
+        assert_eq!(
+            to_latex(&parse("(p v q => r) <=> 1 ^ ~s")),
+            "p \\lor q \\rightarrow r \\leftrightarrow \\top \\land \\neg s"
+        );
+        assert_eq!(
+            to_latex(&parse("(p + q) nand 0")),
+            "(p \\oplus q) \\uparrow \\bot"
+        );
+    }
+
+    #[test]
+    fn test_to_latex_chained_operators() {
+        assert_eq!(to_latex(&parse("p ^ q ^ r")), "p \\land q \\land r");
+        assert_eq!(to_latex(&parse("p ^ (q ^ r)")), "p \\land (q \\land r)");
+        assert_eq!(
+            to_latex(&parse("p => q => r")),
+            "p \\rightarrow q \\rightarrow r"
+        );
+        assert_eq!(
+            to_latex(&parse("(p => q) => r")),
+            "(p \\rightarrow q) \\rightarrow r"
+        );
+    }
+}
@@ -0,0 +1,8 @@
+mod dimacs;
+mod html;
+mod latex;
+mod verilog;
+pub use dimacs::to_dimacs;
+pub use html::to_html;
+pub use latex::to_latex;
+pub use verilog::to_verilog;
@@ -0,0 +1,101 @@
+use anyhow::{anyhow, Result};
+
+use crate::lexer::{Operator, Token, Value};
+use crate::parser::ASTNode;
+
+fn negated(node: &ASTNode) -> Result<String> {
+    Ok(format!("(!{})", operand(node)?))
+}
+
+/// Operand parenthesized unless it's a single signal or constant,
+/// so the expression doesn't depend on the precedence of Verilog operators
+fn operand(node: &ASTNode) -> Result<String> {
+    match node.token {
+        Token::Value(_) => expression(node),
+        Token::Operator(_) => Ok(format!("({})", expression(node)?)),
+    }
+}
+
+fn expression(node: &ASTNode) -> Result<String> {
+    let expression = match (&node.token, node.left.as_deref(), node.right.as_deref()) {
+        (Token::Value(Value::Bool(true)), _, _) => "1'b1".to_string(),
+        (Token::Value(Value::Bool(false)), _, _) => "1'b0".to_string(),
+        (Token::Value(Value::Variable(name)), _, _) => name.clone(),
+        (Token::Operator(Operator::Not), Some(inner), _) => format!("!{}", operand(inner)?),
+        (Token::Operator(Operator::Not), None, _) => {
+            return Err(anyhow!("Cannot export negation without value"))
+        }
+        (Token::Operator(op), Some(left), Some(right)) => match op {
+            Operator::And => format!("{} & {}", operand(left)?, operand(right)?),
+            Operator::Or => format!("{} | {}", operand(left)?, operand(right)?),
+            Operator::Xor => format!("{} ^ {}", operand(left)?, operand(right)?),
+            Operator::Nand => format!("!({} & {})", operand(left)?, operand(right)?),
+            Operator::Nor => format!("!({} | {})", operand(left)?, operand(right)?),
+            Operator::Implication => format!("{} | {}", negated(left)?, operand(right)?),
+            Operator::Equivalence => format!(
+                "({} & {}) | ({} & {})",
+                operand(left)?,
+                operand(right)?,
+                negated(left)?,
+                negated(right)?
+            ),
+            other => return Err(anyhow!("Unexpected operator {:?}", other)),
+        },
+        (Token::Operator(op), _, _) if op.is_binary() => {
+            return Err(anyhow!("Expected two values for infix function"))
+        }
+        (Token::Operator(op), _, _) => return Err(anyhow!("Unexpected operator {:?}", op)),
+    };
+    Ok(expression)
+}
+
+/// Continuous assignment of the formula to the signal, with the variables as wires.
+/// Implication and equivalence have no Verilog operator, so they are written with `&`, `|` and `!`.
+/// Fails on trees the parser doesn't build, like an operator missing an operand.
+pub fn to_verilog(node: &ASTNode, signal: &str) -> Result<String> {
+    Ok(format!("assign {} = {};", signal, expression(node)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::{Operator, Token, Value};
+    use crate::parser::{parse, ASTNode};
+
+    use super::to_verilog;
+
+    #[test]
+    fn test_to_verilog_implication() {
+        assert_eq!(
+            to_verilog(&parse("p => q"), "out").unwrap(),
+            "assign out = (!p) | q;"
+        );
+    }
+
+    #[test]
+    fn test_to_verilog_parenthesizes_operands() {
+        assert_eq!(
+            to_verilog(&parse("p ^ q v ~r + 1"), "y").unwrap(),
+            "assign y = (p & q) | ((!r) ^ 1'b1);"
+        );
+        assert_eq!(
+            to_verilog(&parse("p <=> q nand 0"), "eq").unwrap(),
+            "assign eq = (p & (!(q & 1'b0))) | ((!p) & (!(!(q & 1'b0))));"
+        );
+    }
+
+    #[test]
+    fn test_to_verilog_rejects_malformed_tree() {
+        let mut missing = ASTNode::new(Token::Operator(Operator::And));
+        missing.add_left_token(Token::Value(Value::Variable("p".to_string())));
+        let paren = ASTNode::new(Token::Operator(Operator::ParenthisOpen));
+
+        assert_eq!(
+            to_verilog(&missing, "y").unwrap_err().to_string(),
+            "Expected two values for infix function"
+        );
+        assert_eq!(
+            to_verilog(&paren, "y").unwrap_err().to_string(),
+            "Unexpected operator ParenthisOpen"
+        );
+    }
+}
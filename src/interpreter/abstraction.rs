@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+use crate::lexer::{Operator, Token, Value};
+use crate::parser::{ASTNode, StoredVariables, MAX_DEPTH};
+
+use super::{binary_function, descend, evaluate_ref};
+
+/// Abstract domain of booleans, ordered as a lattice with `Maybe` on top:
+///
+/// ```text
+///          Maybe
+///         /     \
+///  MustFalse   MustTrue
+/// ```
+///
+/// A value stands for every concrete boolean it may take,
+/// `Maybe` being both of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbstractBool {
+    MustTrue,
+    MustFalse,
+    Maybe,
+}
+
+impl AbstractBool {
+    fn concretize(self) -> &'static [bool] {
+        match self {
+            AbstractBool::MustTrue => &[true],
+            AbstractBool::MustFalse => &[false],
+            AbstractBool::Maybe => &[false, true],
+        }
+    }
+
+    /// Least upper bound of the concrete values
+    fn abstract_of(values: impl IntoIterator<Item = bool>) -> AbstractBool {
+        let (mut may_be_true, mut may_be_false) = (false, false);
+        for value in values {
+            may_be_true |= value;
+            may_be_false |= !value;
+        }
+        match (may_be_true, may_be_false) {
+            (true, false) => AbstractBool::MustTrue,
+            (false, true) => AbstractBool::MustFalse,
+            _ => AbstractBool::Maybe,
+        }
+    }
+}
+
+impl From<bool> for AbstractBool {
+    fn from(value: bool) -> AbstractBool {
+        if value {
+            AbstractBool::MustTrue
+        } else {
+            AbstractBool::MustFalse
+        }
+    }
+}
+
+impl fmt::Display for AbstractBool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbstractBool::MustTrue => write!(f, "must-true"),
+            AbstractBool::MustFalse => write!(f, "must-false"),
+            AbstractBool::Maybe => write!(f, "maybe"),
+        }
+    }
+}
+
+/// Direction in which the abstract result may differ from the concrete results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Soundness {
+    /// Over-approximation: every concrete result is one the abstract result stands for,
+    /// though some it stands for may never occur
+    May,
+    /// Under-approximation: the other way around, every concrete value the abstract result
+    /// stands for occurs for some inputs, though some results may be left out
+    Must,
+}
+
+/// Evaluates the formula over abstract inputs, over-approximating the result.
+/// Every operator is lifted by applying it to all the concrete values its operands stand for,
+/// so the result is sound: the concrete result is always one the abstract result stands for.
+/// Operands are abstracted independently, so e.g. `p v ~p` with `p` being `Maybe`
+/// gives `Maybe` rather than `MustTrue`. Trees deeper than `MAX_DEPTH` are rejected.
+pub fn evaluate_abstract(
+    node: &ASTNode,
+    vars: &HashMap<String, AbstractBool>,
+) -> Result<AbstractBool> {
+    evaluate_abstract_with(node, vars, Soundness::May)
+}
+
+/// Same as `evaluate_abstract` in the given direction. Under-approximating, the formula
+/// is evaluated concretely with every `Maybe` input false and then with every one true.
+/// Both results do occur, so `p v ~p` gives `MustTrue`, while `p ^ ~q` with both inputs
+/// `Maybe` gives `MustFalse`, though it's true for `p` true and `q` false.
+pub fn evaluate_abstract_with(
+    node: &ASTNode,
+    vars: &HashMap<String, AbstractBool>,
+    soundness: Soundness,
+) -> Result<AbstractBool> {
+    match soundness {
+        Soundness::May => lift(node, vars, MAX_DEPTH),
+        Soundness::Must => {
+            let mut results = vec![];
+            for maybe in [false, true] {
+                let concrete: StoredVariables = vars
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            AbstractBool::MustTrue => true,
+                            AbstractBool::MustFalse => false,
+                            AbstractBool::Maybe => maybe,
+                        };
+                        (name.clone(), value)
+                    })
+                    .collect();
+                results.push(evaluate_ref(node, &concrete)?);
+            }
+            Ok(AbstractBool::abstract_of(results))
+        }
+    }
+}
+
+/// Lifted value of the node, with `depth` levels left for the node and its operands
+fn lift(
+    node: &ASTNode,
+    vars: &HashMap<String, AbstractBool>,
+    depth: usize,
+) -> Result<AbstractBool> {
+    let depth = descend(depth)?;
+    match &node.token {
+        Token::Value(Value::Bool(value)) => Ok(AbstractBool::from(*value)),
+        Token::Value(Value::Variable(var)) => match vars.get(var) {
+            Some(value) => Ok(*value),
+            None => Err(anyhow!("Undefined variable {}", var)),
+        },
+        Token::Operator(Operator::Not) => match &node.left {
+            Some(left) => {
+                let value = lift(left, vars, depth)?;
+                Ok(AbstractBool::abstract_of(
+                    value.concretize().iter().map(|v| !v),
+                ))
+            }
+            None => Err(anyhow!("Cannot evaluate negation without value")),
+        },
+        Token::Operator(op) => {
+            let func = match binary_function(op) {
+                Some(func) => func,
+                None => return Err(anyhow!("Unexpected operator {:?}", op)),
+            };
+            match (&node.left, &node.right) {
+                (Some(left), Some(right)) => {
+                    let left = lift(left, vars, depth)?;
+                    let right = lift(right, vars, depth)?;
+                    Ok(AbstractBool::abstract_of(
+                        left.concretize()
+                            .iter()
+                            .flat_map(|l| right.concretize().iter().map(move |r| func(*l, *r))),
+                    ))
+                }
+                _ => Err(anyhow!("Expected two values for infix function")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rstest::rstest;
+
+    use crate::lexer::{Operator, Token};
+    use crate::parser::{parse, MAX_DEPTH};
+
+    use super::AbstractBool::{self, Maybe, MustFalse, MustTrue};
+    use super::{evaluate_abstract, evaluate_abstract_with, Soundness};
+
+    #[rstest]
+    #[case("p ^ q", Maybe, MustFalse, MustFalse)]
+    #[case("p ^ q", Maybe, MustTrue, Maybe)]
+    #[case("p v q", Maybe, MustTrue, MustTrue)]
+    #[case("p => q", Maybe, MustTrue, MustTrue)]
+    #[case("p => q", MustTrue, Maybe, Maybe)]
+    #[case("p <=> q", MustFalse, MustFalse, MustTrue)]
+    #[case("~p v q", Maybe, MustFalse, Maybe)]
+    fn test_evaluate_abstract(
+        #[case] expr: &str,
+        #[case] p: AbstractBool,
+        #[case] q: AbstractBool,
+        #[case] expected: AbstractBool,
+    ) {
+        let vars = HashMap::from([("p".to_string(), p), ("q".to_string(), q)]);
+
+        let result = evaluate_abstract(&parse(expr), &vars).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_evaluate_abstract_is_not_relational() {
+        let vars = HashMap::from([("p".to_string(), Maybe)]);
+
+        let result = evaluate_abstract(&parse("p v ~p"), &vars).unwrap();
+
+        assert_eq!(result, Maybe);
+    }
+
+    #[rstest]
+    #[case("p v ~p", Maybe, Maybe, Maybe, MustTrue)]
+    #[case("p ^ ~q", Maybe, Maybe, Maybe, MustFalse)]
+    #[case("p <=> q", Maybe, Maybe, Maybe, MustTrue)]
+    #[case("p ^ q", Maybe, Maybe, Maybe, Maybe)]
+    #[case("p ^ q", Maybe, MustFalse, MustFalse, MustFalse)]
+    fn test_evaluate_abstract_soundness(
+        #[case] expr: &str,
+        #[case] p: AbstractBool,
+        #[case] q: AbstractBool,
+        #[case] may: AbstractBool,
+        #[case] must: AbstractBool,
+    ) {
+        let vars = HashMap::from([("p".to_string(), p), ("q".to_string(), q)]);
+        let root = parse(expr);
+
+        assert_eq!(
+            evaluate_abstract_with(&root, &vars, Soundness::May).unwrap(),
+            may
+        );
+        assert_eq!(
+            evaluate_abstract_with(&root, &vars, Soundness::Must).unwrap(),
+            must
+        );
+    }
+
+    #[test]
+    fn test_evaluate_abstract_depth_limit() {
+        let negations = (0..MAX_DEPTH).fold(parse("p"), |node, _| {
+            node.make_new_root_left(Token::Operator(Operator::Not))
+        });
+        let vars = HashMap::from([("p".to_string(), Maybe)]);
+
+        let result = evaluate_abstract(&negations, &vars);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Expression too deeply nested"
+        );
+    }
+}
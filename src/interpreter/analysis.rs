@@ -0,0 +1,430 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{anyhow, Result};
+
+use crate::lexer::{Operator, Token, Value};
+use crate::parser::{ASTNode, StoredVariables};
+
+use super::{assignments, binary_function, descend, evaluate_ref, EvalOptions};
+
+/// Partial evaluation of the formula with some of the variables held fixed.
+/// Fixed variables are substituted and the resulting constants folded, so what is left
+/// is the residual formula over the remaining free variables.
+pub fn project(root: &ASTNode, fixed: &HashMap<String, bool>) -> Result<ASTNode> {
+    project_with(root, fixed, &EvalOptions::default())
+}
+
+/// Same as `project`, with trees deeper than the `max_depth` of the options rejected
+pub fn project_with(
+    root: &ASTNode,
+    fixed: &HashMap<String, bool>,
+    options: &EvalOptions,
+) -> Result<ASTNode> {
+    project_node(root, fixed, options.max_depth)
+}
+
+fn project_node(root: &ASTNode, fixed: &HashMap<String, bool>, depth: usize) -> Result<ASTNode> {
+    let depth = descend(depth)?;
+    match &root.token {
+        Token::Value(Value::Variable(var)) => match fixed.get(var) {
+            Some(value) => Ok(ASTNode::new(Token::Value(Value::Bool(*value)))),
+            None => Ok(root.clone()),
+        },
+        Token::Value(Value::Bool(_)) => Ok(root.clone()),
+        Token::Operator(Operator::Not) => match &root.left {
+            Some(left) => {
+                let inner = project_node(left, fixed, depth)?;
+                Ok(fold_negation(inner))
+            }
+            None => Err(anyhow!("Cannot evaluate negation without value")),
+        },
+        Token::Operator(op) => {
+            let func = match binary_function(op) {
+                Some(func) => func,
+                None => return Err(anyhow!("Unexpected operator {:?}", op)),
+            };
+            match (&root.left, &root.right) {
+                (Some(left), Some(right)) => {
+                    let left = project_node(left, fixed, depth)?;
+                    let right = project_node(right, fixed, depth)?;
+                    Ok(fold_binary(op.clone(), left, right, func))
+                }
+                (Some(left), None) => Err(anyhow!(
+                    "Expected two values for infix function, got only left: {}",
+                    left
+                )),
+                (None, Some(right)) => Err(anyhow!(
+                    "Expected two values for infix function, got only right: {}",
+                    right
+                )),
+                _ => Err(anyhow!("Expected two values for infix function, got none")),
+            }
+        }
+    }
+}
+
+/// Boolean derivative of the formula with respect to the variable, `f|var=0 + f|var=1`.
+/// The result is a formula over the remaining variables, which is true exactly
+/// where flipping the variable changes the value of the formula.
+pub fn boolean_derivative(root: &ASTNode, var: &str) -> Result<ASTNode> {
+    let negative = project(root, &HashMap::from([(var.to_string(), false)]))?;
+    let positive = project(root, &HashMap::from([(var.to_string(), true)]))?;
+    if negative == positive {
+        return Ok(ASTNode::new(Token::Value(Value::Bool(false))));
+    }
+    let xor = binary_function(&Operator::Xor).expect("XOR is an infix operator");
+    Ok(fold_binary(Operator::Xor, negative, positive, xor))
+}
+
+/// Checks whether the conclusion holds under every assignment satisfying the premise.
+/// Decided by enumerating the assignments, so it's meant for small variable sets.
+pub fn entails(premise: &ASTNode, conclusion: &ASTNode) -> Result<bool> {
+    let mut variables = premise.free_variables();
+    variables.extend(conclusion.free_variables());
+    let variables: Vec<String> = variables.into_iter().collect();
+
+    for values in assignments(&variables)? {
+        let assigned: StoredVariables = variables.iter().cloned().zip(values).collect();
+        if evaluate_ref(premise, &assigned)? && !evaluate_ref(conclusion, &assigned)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Drops the clauses of a CNF that are entailed by the remaining ones.
+/// Clauses are visited in order and a clause is kept whenever the others don't imply it,
+/// so out of mutually redundant clauses the last one survives.
+pub fn remove_redundant_clauses(cnf: &ASTNode) -> Result<ASTNode> {
+    let mut clauses: Vec<ASTNode> = cnf.conjuncts().into_iter().cloned().collect();
+    let mut index = 0;
+    while index < clauses.len() {
+        let others = clauses
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, clause)| clause.clone())
+            .reduce(conjunction);
+        match others {
+            Some(others) if entails(&others, &clauses[index])? => {
+                clauses.remove(index);
+            }
+            _ => index += 1,
+        }
+    }
+    let mut clauses = clauses.into_iter();
+    let first = clauses
+        .next()
+        .ok_or_else(|| anyhow!("Expected at least one clause"))?;
+    Ok(clauses.fold(first, conjunction))
+}
+
+/// Probability of the formula being true when the variables are independent
+/// and each is true with the given probability
+pub fn probability(root: &ASTNode, probs: &HashMap<String, f64>) -> Result<f64> {
+    let variables: Vec<String> = root.free_variables().into_iter().collect();
+    let mut weights = vec![];
+    for var in &variables {
+        match probs.get(var) {
+            Some(p) if (0.0..=1.0).contains(p) => weights.push(*p),
+            Some(p) => {
+                return Err(anyhow!(
+                    "Probability of {} has to be within [0, 1], got {}",
+                    var,
+                    p
+                ))
+            }
+            None => return Err(anyhow!("Missing probability of variable {}", var)),
+        }
+    }
+
+    let mut total = 0.0;
+    for values in assignments(&variables)? {
+        let weight: f64 = values
+            .iter()
+            .zip(&weights)
+            .map(|(value, p)| if *value { *p } else { 1.0 - p })
+            .product();
+        let assigned: StoredVariables = variables.iter().cloned().zip(values).collect();
+        if evaluate_ref(root, &assigned)? {
+            total += weight;
+        }
+    }
+    Ok(total)
+}
+
+/// Results of the formula over all the assignments, in truth-table order
+fn results(root: &ASTNode, vars: &[String]) -> Result<Vec<bool>> {
+    let mut results = vec![];
+    for values in assignments(vars)? {
+        let assigned: StoredVariables = vars.iter().cloned().zip(values).collect();
+        results.push(evaluate_ref(root, &assigned)?);
+    }
+    Ok(results)
+}
+
+/// Checks that raising any single input from false to true never lowers the result.
+/// Any two comparable assignments are connected by a chain of such raises,
+/// so checking neighbouring rows covers the whole assignment lattice.
+pub fn is_monotone(root: &ASTNode, vars: &[String]) -> Result<bool> {
+    let results = results(root, vars)?;
+    for (row, result) in results.iter().enumerate() {
+        for bit in 0..vars.len() {
+            let raised = row | (1 << bit);
+            if *result && !results[raised] {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Free variables whose value matters, i.e. flipping it changes the result under
+/// at least one assignment of the others. `q` doesn't matter in `(p ^ q) v p`.
+pub fn relevant_variables(root: &ASTNode) -> Result<BTreeSet<String>> {
+    let vars: Vec<String> = root.free_variables().into_iter().collect();
+    let results = results(root, &vars)?;
+    Ok(vars
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            // The first variable is the most significant bit of the row number
+            let bit = 1 << (vars.len() - 1 - index);
+            (0..results.len()).any(|row| results[row] != results[row ^ bit])
+        })
+        .map(|(_, var)| var.clone())
+        .collect())
+}
+
+fn conjunction(left: ASTNode, right: ASTNode) -> ASTNode {
+    ASTNode {
+        token: Token::Operator(Operator::And),
+        left: Some(Box::new(left)),
+        right: Some(Box::new(right)),
+    }
+}
+
+fn constant(node: &ASTNode) -> Option<bool> {
+    match node.token {
+        Token::Value(Value::Bool(value)) => Some(value),
+        _ => None,
+    }
+}
+
+fn negate(node: ASTNode) -> ASTNode {
+    node.make_new_root_left(Token::Operator(Operator::Not))
+}
+
+fn fold_negation(operand: ASTNode) -> ASTNode {
+    match constant(&operand) {
+        Some(value) => ASTNode::new(Token::Value(Value::Bool(!value))),
+        None => negate(operand),
+    }
+}
+
+/// Folds one node over its already folded operands. Operators missing an operand
+/// are rebuilt as they are, rejecting them is up to the caller.
+pub(crate) fn fold_node(token: Token, left: Option<ASTNode>, right: Option<ASTNode>) -> ASTNode {
+    match (&token, left, right) {
+        (Token::Operator(Operator::Not), Some(operand), None) => fold_negation(operand),
+        (Token::Operator(op), Some(left), Some(right)) => match binary_function(op) {
+            Some(func) => fold_binary(op.clone(), left, right, func),
+            None => ASTNode {
+                token,
+                left: Some(Box::new(left)),
+                right: Some(Box::new(right)),
+            },
+        },
+        (_, left, right) => ASTNode {
+            token,
+            left: left.map(Box::new),
+            right: right.map(Box::new),
+        },
+    }
+}
+
+/// When one side is a constant, the truth function degenerates to a function
+/// of the other side: constant, identity or negation.
+fn fold_binary(
+    operator: Operator,
+    left: ASTNode,
+    right: ASTNode,
+    func: fn(bool, bool) -> bool,
+) -> ASTNode {
+    let reduce = |on_false: bool, on_true: bool, other: ASTNode| match (on_false, on_true) {
+        (false, true) => other,
+        (true, false) => negate(other),
+        (value, _) => ASTNode::new(Token::Value(Value::Bool(value))),
+    };
+    match (constant(&left), constant(&right)) {
+        (Some(l_value), Some(r_value)) => {
+            ASTNode::new(Token::Value(Value::Bool(func(l_value, r_value))))
+        }
+        (Some(l_value), None) => reduce(func(l_value, false), func(l_value, true), right),
+        (None, Some(r_value)) => reduce(func(false, r_value), func(true, r_value), left),
+        (None, None) => ASTNode {
+            token: Token::Operator(operator),
+            left: Some(Box::new(left)),
+            right: Some(Box::new(right)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashMap};
+
+    use crate::lexer::{Token, Value};
+    use crate::parser::{parse, ASTNode};
+
+    use super::{
+        boolean_derivative, entails, is_monotone, probability, project, project_with,
+        relevant_variables, remove_redundant_clauses, EvalOptions,
+    };
+
+    #[test]
+    fn test_project_true_leaves_residual() {
+        let root = parse("p ^ (q v r)");
+        let fixed = HashMap::from([("p".to_string(), true)]);
+
+        let result = project(&root, &fixed).unwrap();
+
+        assert_eq!(result, parse("q v r"));
+    }
+
+    #[test]
+    fn test_project_false_folds_to_constant() {
+        let root = parse("p ^ (q v r)");
+        let fixed = HashMap::from([("p".to_string(), false)]);
+
+        let result = project(&root, &fixed).unwrap();
+
+        assert_eq!(result, ASTNode::new(Token::Value(Value::Bool(false))));
+    }
+
+    #[test]
+    fn test_project_negates_residual() {
+        let root = parse("p => q");
+        let fixed = HashMap::from([("q".to_string(), false)]);
+
+        let result = project(&root, &fixed).unwrap();
+
+        assert_eq!(result, parse("~p"));
+    }
+
+    #[test]
+    fn test_entails() {
+        assert!(entails(&parse("p ^ q"), &parse("p v r")).unwrap());
+        assert!(!entails(&parse("p v q"), &parse("p")).unwrap());
+    }
+
+    #[test]
+    fn test_remove_redundant_clauses() {
+        let cnf = parse("(p v q) ^ (p v q v r)");
+
+        let result = remove_redundant_clauses(&cnf).unwrap();
+
+        assert_eq!(result, parse("p v q"));
+    }
+
+    #[test]
+    fn test_remove_redundant_clauses_drops_resolvent() {
+        let cnf = parse("(p v q) ^ (~p v r) ^ (q v r)");
+
+        let result = remove_redundant_clauses(&cnf).unwrap();
+
+        assert_eq!(result, parse("(p v q) ^ (~p v r)"));
+    }
+
+    #[test]
+    fn test_probability_of_disjunction() {
+        let probs = HashMap::from([("p".to_string(), 0.5), ("q".to_string(), 0.5)]);
+
+        let result = probability(&parse("p v q"), &probs).unwrap();
+
+        assert!((result - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_probability_should_fail_outside_unit_interval() {
+        let probs = HashMap::from([("p".to_string(), 1.5)]);
+
+        let result = probability(&parse("~p"), &probs);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_probability_should_fail_on_missing_variable() {
+        let probs = HashMap::from([("p".to_string(), 0.2)]);
+
+        let result = probability(&parse("p ^ q"), &probs);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_is_monotone() {
+        let vars = vec!["p".to_string(), "q".to_string()];
+
+        assert!(is_monotone(&parse("p v q"), &vars).unwrap());
+        assert!(is_monotone(&parse("p ^ q"), &vars).unwrap());
+        assert!(!is_monotone(&parse("p ^ ~q"), &vars).unwrap());
+        assert!(!is_monotone(&parse("p => q"), &vars).unwrap());
+    }
+
+    #[test]
+    fn test_boolean_derivative_of_conjunction() {
+        let result = boolean_derivative(&parse("p ^ q"), "p").unwrap();
+
+        assert_eq!(result, parse("q"));
+    }
+
+    #[test]
+    fn test_boolean_derivative_is_equivalent_to_sensitivity() {
+        let result = boolean_derivative(&parse("(p v q) ^ (p => r)"), "p").unwrap();
+
+        let expected = parse("(q ^ ~r) v (~q ^ r)");
+        assert!(entails(&result, &expected).unwrap());
+        assert!(entails(&expected, &result).unwrap());
+        assert!(!result.free_variables().contains("p"));
+    }
+
+    #[test]
+    fn test_boolean_derivative_of_independent_variable() {
+        let result = boolean_derivative(&parse("q v r"), "p").unwrap();
+
+        assert_eq!(result, parse("0"));
+    }
+
+    #[test]
+    fn test_relevant_variables() {
+        let names = |names: &[&str]| -> BTreeSet<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        };
+
+        assert_eq!(
+            relevant_variables(&parse("(p ^ q) v p")).unwrap(),
+            names(&["p"])
+        );
+        assert_eq!(
+            relevant_variables(&parse("(p => q) ^ r")).unwrap(),
+            names(&["p", "q", "r"])
+        );
+        assert!(relevant_variables(&parse("p v ~p v q")).unwrap().is_empty());
+        assert!(relevant_variables(&parse("1 ^ 0")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_project_depth_limit() {
+        let root = parse("~~p ^ q");
+        let fixed = HashMap::from([("p".to_string(), true)]);
+        let within = |max_depth| project_with(&root, &fixed, &EvalOptions { max_depth });
+
+        assert_eq!(
+            within(3).unwrap_err().to_string(),
+            "Expression too deeply nested"
+        );
+        assert_eq!(within(4).unwrap(), parse("q"));
+    }
+}
@@ -1,61 +1,569 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+
 use anyhow::{anyhow, Result};
 
-use crate::lexer::{Operator, Token, Value};
-use crate::parser::{ASTNode, StoredVariables};
+use crate::lexer::{Operator, Span, Token, Value};
+use crate::parser::{parenthesized, ASTNode, StoredVariables, MAX_DEPTH};
+mod abstraction;
+mod analysis;
+pub use abstraction::{evaluate_abstract, evaluate_abstract_with, AbstractBool, Soundness};
+pub(crate) use analysis::fold_node;
+pub use analysis::{
+    boolean_derivative, entails, is_monotone, probability, project, project_with,
+    relevant_variables, remove_redundant_clauses,
+};
 
+/// Evaluates the formula taking the tree by value, a thin wrapper over `evaluate_ref`
 pub fn evaluate(node: ASTNode, vars: &StoredVariables) -> Result<bool> {
-    match node.token {
+    evaluate_ref(&node, vars)
+}
+
+/// Limits of the evaluator
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvalOptions {
+    /// Deepest tree evaluated, `MAX_DEPTH` unless changed
+    pub max_depth: usize,
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        EvalOptions {
+            max_depth: MAX_DEPTH,
+        }
+    }
+}
+
+/// Same as `evaluate`, but borrows the tree so it can be evaluated again.
+/// Trees deeper than `MAX_DEPTH` are rejected instead of risking a stack overflow.
+pub fn evaluate_ref(node: &ASTNode, vars: &StoredVariables) -> Result<bool> {
+    evaluate_ref_with(node, vars, &EvalOptions::default())
+}
+
+/// Same as `evaluate_ref` within the given limits. The depth is counted along the way,
+/// so an operand skipped by the short-circuit of `^` and `v` may be deeper.
+pub fn evaluate_ref_with(
+    node: &ASTNode,
+    vars: &StoredVariables,
+    options: &EvalOptions,
+) -> Result<bool> {
+    eval_node(node, vars, options.max_depth)
+}
+
+/// Levels left for the operands of a node with `depth` levels left for itself
+/// and its operands, failing when there are none
+pub(crate) fn descend(depth: usize) -> Result<usize> {
+    depth
+        .checked_sub(1)
+        .ok_or_else(|| anyhow!("Expression too deeply nested"))
+}
+
+fn eval_node(node: &ASTNode, vars: &StoredVariables, depth: usize) -> Result<bool> {
+    let depth = descend(depth)?;
+    match &node.token {
         Token::Value(value) => match value {
-            Value::Bool(val) => Ok(val),
-            Value::Variable(var) => match vars.get(&var) {
+            Value::Bool(val) => Ok(*val),
+            Value::Variable(var) => match vars.get(var) {
                 Some(var_value) => Ok(*var_value),
-                None => Err(anyhow!("Undefined variable {}", var)),
+                None => Err(undefined_variable(var, vars)),
             },
         },
         Token::Operator(op) => match op {
-            Operator::Equivalence => eval_binary(node.left, node.right, vars, |a, b| a == b),
-            Operator::Implication => eval_binary(node.left, node.right, vars, implication),
-            Operator::Or => eval_binary(node.left, node.right, vars, |a, b| a || b),
-            Operator::And => eval_binary(node.left, node.right, vars, |a, b| a && b),
-            Operator::Not => match node.left {
-                Some(left) => Ok(!evaluate(*left, vars)?),
+            Operator::Not => match node.left.as_deref() {
+                Some(left) => Ok(!eval_node(left, vars, depth)?),
                 None => Err(anyhow!("Cannot evaluate negation without value")),
             },
-            other => Err(anyhow!("Unexpected operator {:?}", other)),
+            // The right operand is skipped when the left one already decides the result
+            Operator::And | Operator::Or => match (node.left.as_deref(), node.right.as_deref()) {
+                (Some(left), Some(right)) => match (op, eval_node(left, vars, depth)?) {
+                    (Operator::And, false) => Ok(false),
+                    (Operator::Or, true) => Ok(true),
+                    _ => eval_node(right, vars, depth),
+                },
+                _ => Err(anyhow!("Expected two values for infix function")),
+            },
+            other => match binary_function(other) {
+                Some(func) => eval_binary(
+                    node.left.as_deref(),
+                    node.right.as_deref(),
+                    vars,
+                    depth,
+                    func,
+                ),
+                None => Err(anyhow!("Unexpected operator {:?}", other)),
+            },
         },
     }
 }
 
-fn eval_binary(
-    l_node: Option<Box<ASTNode>>,
-    r_node: Option<Box<ASTNode>>,
+/// Error listing the defined variables, with the closest one suggested
+/// when it's at most two edits away
+fn undefined_variable(var: &str, vars: &StoredVariables) -> anyhow::Error {
+    undefined_variables(&[(var, None)], vars)
+}
+
+/// Error naming the undefined variables, at their position when it's known, and listing
+/// the defined ones. For a single variable the closest defined one is suggested
+/// when it's at most two edits away.
+pub(crate) fn undefined_variables(
+    undefined: &[(&str, Option<&Span>)],
     vars: &StoredVariables,
+) -> anyhow::Error {
+    let mut defined: Vec<&String> = vars.keys().collect();
+    defined.sort();
+    let listed: Vec<&str> = defined.iter().map(|name| name.as_str()).collect();
+    let named: Vec<String> = undefined
+        .iter()
+        .map(|(var, span)| match span {
+            Some(span) => format!("'{}' at {}", var, span),
+            None => format!("'{}'", var),
+        })
+        .collect();
+    let message = format!(
+        "Undefined variable{} {}; defined: [{}]",
+        if undefined.len() == 1 { "" } else { "s" },
+        named.join(", "),
+        listed.join(", ")
+    );
+    let closest = match undefined {
+        [(var, _)] => defined
+            .iter()
+            .map(|name| (edit_distance(var, name), name))
+            .filter(|(distance, _)| *distance <= 2)
+            .min(),
+        _ => None,
+    };
+    match closest {
+        Some((_, name)) => anyhow!("{}; did you mean '{}'?", message, name),
+        None => anyhow!(message),
+    }
+}
+
+/// Levenshtein distance, the number of characters to insert, remove or replace
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a_char != *b_char);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Evaluates the formula recording a line for every operator, from the innermost one,
+/// e.g. `p ^ q = true ^ false = false`. Like `evaluate_ref`, `^` and `v` skip the right
+/// operand when the left one decides the result, and their line shows it unevaluated.
+pub fn evaluate_trace(node: &ASTNode, vars: &StoredVariables) -> Result<(bool, Vec<String>)> {
+    evaluate_trace_with(node, vars, &EvalOptions::default())
+}
+
+/// Same as `evaluate_trace` within the given limits
+pub fn evaluate_trace_with(
+    node: &ASTNode,
+    vars: &StoredVariables,
+    options: &EvalOptions,
+) -> Result<(bool, Vec<String>)> {
+    let mut steps = vec![];
+    let (result, _) = eval_traced(node, vars, options.max_depth, &mut steps)?;
+    Ok((result, steps))
+}
+
+/// Value of the node along with its rendering, built from the renderings of its operands
+/// so that every subexpression is rendered only once
+fn eval_traced(
+    node: &ASTNode,
+    vars: &StoredVariables,
+    depth: usize,
+    steps: &mut Vec<String>,
+) -> Result<(bool, String)> {
+    let inner = descend(depth)?;
+    match (&node.token, node.left.as_deref(), node.right.as_deref()) {
+        (Token::Operator(Operator::Not), Some(operand), _) => {
+            let (value, rendered) = eval_traced(operand, vars, inner, steps)?;
+            let rendered = format!(
+                "~{}",
+                parenthesized(&operand.token, rendered, &Operator::Not, false)
+            );
+            steps.push(format!("{} = ~{} = {}", rendered, value, !value));
+            Ok((!value, rendered))
+        }
+        (Token::Operator(op), Some(left), Some(right)) => {
+            let func =
+                binary_function(op).ok_or_else(|| anyhow!("Unexpected operator {:?}", op))?;
+            let (l_result, l_rendered) = eval_traced(left, vars, inner, steps)?;
+            let decided = matches!(
+                (op, l_result),
+                (Operator::And, false) | (Operator::Or, true)
+            );
+            let (r_shown, r_rendered, result) = if decided {
+                let rendered = parenthesized(&right.token, right.to_expr_string(), op, true);
+                (rendered.clone(), rendered, l_result)
+            } else {
+                let (r_result, rendered) = eval_traced(right, vars, inner, steps)?;
+                let rendered = parenthesized(&right.token, rendered, op, true);
+                (r_result.to_string(), rendered, func(l_result, r_result))
+            };
+            let rendered = format!(
+                "{} {} {}",
+                parenthesized(&left.token, l_rendered, op, false),
+                op.symbol(),
+                r_rendered
+            );
+            steps.push(format!(
+                "{} = {} {} {} = {}",
+                rendered,
+                l_result,
+                op.symbol(),
+                r_shown,
+                result
+            ));
+            Ok((result, rendered))
+        }
+        // Values, and the malformed operators failing the same way as in `evaluate_ref`
+        _ => Ok((eval_node(node, vars, depth)?, node.to_expr_string())),
+    }
+}
+
+/// Same as `evaluate_ref`, but every distinct subtree is evaluated only once.
+/// Subtrees are numbered in a single bottom-up pass by their token and the numbers of
+/// their operands, taken in sorted order for commutative operators, so `p ^ q` and `q ^ p`
+/// share a result. Worth it for formulas with large repeated parts, e.g. after `substitute`.
+/// Like in `evaluate_ref`, `^` and `v` skip the right operand when the left one decides.
+pub fn evaluate_memo(node: &ASTNode, vars: &StoredVariables) -> Result<bool> {
+    evaluate_memo_with(node, vars, &EvalOptions::default())
+}
+
+/// Same as `evaluate_memo` within the given limits
+pub fn evaluate_memo_with(
+    node: &ASTNode,
+    vars: &StoredVariables,
+    options: &EvalOptions,
+) -> Result<bool> {
+    let mut results = vec![];
+    let number = eval_memo(
+        node,
+        vars,
+        options.max_depth,
+        &mut HashMap::new(),
+        &mut results,
+    )?;
+    Ok(results[number])
+}
+
+/// Subtree as told apart by `evaluate_memo`, its token and the numbers of its operands
+type SubtreeKey = (Token, Option<usize>, Option<usize>);
+
+/// Number of the subtree, an index into the results holding one for every distinct subtree.
+/// The subtree is only evaluated when it's the first one with its key. A `^` or `v` decided
+/// by its left operand takes the number of that operand, as they have the same result.
+fn eval_memo(
+    node: &ASTNode,
+    vars: &StoredVariables,
+    depth: usize,
+    numbers: &mut HashMap<SubtreeKey, usize>,
+    results: &mut Vec<bool>,
+) -> Result<usize> {
+    let depth = descend(depth)?;
+    let number_of = |child: &Option<Box<ASTNode>>,
+                     numbers: &mut HashMap<SubtreeKey, usize>,
+                     results: &mut Vec<bool>| {
+        child
+            .as_deref()
+            .map(|child| eval_memo(child, vars, depth, numbers, results))
+            .transpose()
+    };
+    let left = number_of(&node.left, numbers, results)?;
+    if let (Token::Operator(op @ (Operator::And | Operator::Or)), Some(left), Some(_)) =
+        (&node.token, left, &node.right)
+    {
+        if results[left] == (*op == Operator::Or) {
+            return Ok(left);
+        }
+    }
+    let right = number_of(&node.right, numbers, results)?;
+    let (left, right) = match &node.token {
+        Token::Operator(op) if op.is_commutative() && left > right => (right, left),
+        _ => (left, right),
+    };
+    let key = (node.token.clone(), left, right);
+    if let Some(number) = numbers.get(&key) {
+        return Ok(*number);
+    }
+    let operand = |number: Option<usize>| match number {
+        Some(number) => Ok(results[number]),
+        None => Err(anyhow!("Operator {:?} is missing an operand", node.token)),
+    };
+    let result = match &node.token {
+        Token::Value(_) => eval_node(node, vars, 1)?,
+        Token::Operator(Operator::Not) => !operand(left)?,
+        Token::Operator(op) => match binary_function(op) {
+            Some(func) => func(operand(left)?, operand(right)?),
+            None => return Err(anyhow!("Unexpected operator {:?}", op)),
+        },
+    };
+    results.push(result);
+    numbers.insert(key, results.len() - 1);
+    Ok(results.len() - 1)
+}
+
+/// Step of `evaluate_iter`, standing in for a call or a return of the recursive evaluator
+enum Step<'a> {
+    Visit(&'a ASTNode),
+    Negate,
+    /// The left operand is evaluated, the right one may still be needed
+    Right(&'a ASTNode, &'a Operator),
+    /// Both operands are evaluated, the left one is kept here
+    Combine(bool, fn(bool, bool) -> bool),
+}
+
+/// Same as `evaluate_ref`, including the short-circuit of `^` and `v`, but with a stack of
+/// its own instead of recursion, so trees of any depth can be evaluated
+pub fn evaluate_iter(node: &ASTNode, vars: &StoredVariables) -> Result<bool> {
+    let mut steps = vec![Step::Visit(node)];
+    let mut results: Vec<bool> = vec![];
+    let pop_result = |results: &mut Vec<bool>| {
+        results
+            .pop()
+            .ok_or_else(|| anyhow!("Expected a value on the evaluation stack"))
+    };
+    while let Some(step) = steps.pop() {
+        match step {
+            Step::Visit(node) => match (&node.token, node.left.as_deref()) {
+                (Token::Value(_), _) => results.push(eval_node(node, vars, 1)?),
+                (Token::Operator(Operator::Not), Some(left)) => {
+                    steps.push(Step::Negate);
+                    steps.push(Step::Visit(left));
+                }
+                (Token::Operator(Operator::Not), None) => {
+                    return Err(anyhow!("Cannot evaluate negation without value"))
+                }
+                (Token::Operator(op), Some(left)) if node.right.is_some() => {
+                    steps.push(Step::Right(node, op));
+                    steps.push(Step::Visit(left));
+                }
+                (Token::Operator(op), _) if binary_function(op).is_some() => {
+                    return Err(anyhow!("Expected two values for infix function"))
+                }
+                (Token::Operator(op), _) => return Err(anyhow!("Unexpected operator {:?}", op)),
+            },
+            Step::Negate => {
+                let value = pop_result(&mut results)?;
+                results.push(!value);
+            }
+            Step::Right(node, op) => {
+                let left = pop_result(&mut results)?;
+                match (op, left) {
+                    // The left operand decides the result, which is the left operand itself
+                    (Operator::And, false) | (Operator::Or, true) => results.push(left),
+                    _ => {
+                        let func = binary_function(op)
+                            .ok_or_else(|| anyhow!("Unexpected operator {:?}", op))?;
+                        steps.push(Step::Combine(left, func));
+                        if let Some(right) = node.right.as_deref() {
+                            steps.push(Step::Visit(right));
+                        }
+                    }
+                }
+            }
+            Step::Combine(left, func) => {
+                let right = pop_result(&mut results)?;
+                results.push(func(left, right));
+            }
+        }
+    }
+    pop_result(&mut results)
+}
+
+/// Evaluates the formula under values supplied by the caller alone. Assignments made with `:=`
+/// in the source end up in the `StoredVariables` returned by the parser, not in the tree,
+/// so they don't take part here.
+pub fn evaluate_with(node: &ASTNode, overrides: &HashMap<String, bool>) -> Result<bool> {
+    evaluate_ref(node, overrides)
+}
+
+/// Evaluates the formula under the assignments from the source, where the overrides
+/// win over the assigned value of the same variable
+pub fn evaluate_overriding(
+    node: &ASTNode,
+    vars: &StoredVariables,
+    overrides: &HashMap<String, bool>,
+) -> Result<bool> {
+    let mut merged = vars.clone();
+    merged.extend(overrides.iter().map(|(name, value)| (name.clone(), *value)));
+    evaluate_ref(node, &merged)
+}
+
+/// Evaluates the formula asking the resolver for the value of each variable on demand.
+/// Values are remembered for the time of the evaluation, so the resolver is called
+/// at most once per variable.
+pub fn evaluate_with_resolver(
+    node: &ASTNode,
+    resolver: &mut dyn FnMut(&str) -> Option<bool>,
+) -> Result<bool> {
+    evaluate_with_resolver_with(node, resolver, &EvalOptions::default())
+}
+
+/// Same as `evaluate_with_resolver` within the given limits
+pub fn evaluate_with_resolver_with(
+    node: &ASTNode,
+    resolver: &mut dyn FnMut(&str) -> Option<bool>,
+    options: &EvalOptions,
+) -> Result<bool> {
+    let mut resolved = HashMap::new();
+    eval_resolved(node, resolver, &mut resolved, options.max_depth)
+}
+
+fn eval_resolved(
+    node: &ASTNode,
+    resolver: &mut dyn FnMut(&str) -> Option<bool>,
+    resolved: &mut HashMap<String, bool>,
+    depth: usize,
+) -> Result<bool> {
+    let depth = descend(depth)?;
+    match &node.token {
+        Token::Value(Value::Bool(val)) => Ok(*val),
+        Token::Value(Value::Variable(var)) => {
+            if let Some(value) = resolved.get(var) {
+                return Ok(*value);
+            }
+            match resolver(var) {
+                Some(value) => {
+                    resolved.insert(var.clone(), value);
+                    Ok(value)
+                }
+                None => Err(anyhow!("Undefined variable {}", var)),
+            }
+        }
+        Token::Operator(Operator::Not) => match &node.left {
+            Some(left) => Ok(!eval_resolved(left, resolver, resolved, depth)?),
+            None => Err(anyhow!("Cannot evaluate negation without value")),
+        },
+        Token::Operator(op) => match (binary_function(op), &node.left, &node.right) {
+            (Some(_), Some(left), Some(right)) if matches!(op, Operator::And | Operator::Or) => {
+                match (op, eval_resolved(left, resolver, resolved, depth)?) {
+                    (Operator::And, false) => Ok(false),
+                    (Operator::Or, true) => Ok(true),
+                    _ => eval_resolved(right, resolver, resolved, depth),
+                }
+            }
+            (Some(func), Some(left), Some(right)) => {
+                let l_result = eval_resolved(left, resolver, resolved, depth)?;
+                let r_result = eval_resolved(right, resolver, resolved, depth)?;
+                Ok(func(l_result, r_result))
+            }
+            (Some(_), _, _) => Err(anyhow!("Expected two values for infix function")),
+            (None, _, _) => Err(anyhow!("Unexpected operator {:?}", op)),
+        },
+    }
+}
+
+/// Truth function of an infix operator, `None` for the operators that aren't one
+fn binary_function(operator: &Operator) -> Option<fn(bool, bool) -> bool> {
+    match operator {
+        Operator::Equivalence => Some(|a, b| a == b),
+        Operator::Implication => Some(implication),
+        Operator::Or => Some(|a, b| a || b),
+        Operator::Nor => Some(|a, b| !(a || b)),
+        Operator::Xor => Some(|a, b| a != b),
+        Operator::And => Some(|a, b| a && b),
+        Operator::Nand => Some(|a, b| !(a && b)),
+        _ => None,
+    }
+}
+
+fn eval_binary<N: Borrow<ASTNode>>(
+    l_node: Option<N>,
+    r_node: Option<N>,
+    vars: &StoredVariables,
+    depth: usize,
     func: fn(bool, bool) -> bool,
 ) -> Result<bool> {
     match (l_node, r_node) {
         (Some(left), Some(right)) => {
-            let l_result = evaluate(*left, vars)?;
-            let r_result = evaluate(*right, vars)?;
+            let l_result = eval_node(left.borrow(), vars, depth)?;
+            let r_result = eval_node(right.borrow(), vars, depth)?;
             Ok(func(l_result, r_result))
         }
         (Some(left), None) => Err(anyhow!(
             "Expected two values for infix function, got only left: {}",
-            left
+            left.borrow()
         )),
         (None, Some(right)) => Err(anyhow!(
             "Expected two values for infix function, got only right: {}",
-            right
+            right.borrow()
         )),
         _ => Err(anyhow!("Expected two values for infix function, got none")),
     }
 }
 
-fn implication(l_value: bool, r_value: bool) -> bool {
-    if l_value & !r_value {
-        false
-    } else {
-        true
+/// Every assignment of the variables in the standard truth-table order,
+/// where the first variable is the most significant bit
+pub fn assignments(vars: &[String]) -> Result<impl Iterator<Item = Vec<bool>>> {
+    let count = vars.len();
+    if count >= u64::BITS as usize {
+        return Err(anyhow!("Too many variables to enumerate: {}", count));
+    }
+    Ok((0..1u64 << count).map(move |index| {
+        (0..count)
+            .map(|i| (index >> (count - 1 - i)) & 1 == 1)
+            .collect()
+    }))
+}
+
+/// Kind of a formula judged by its results over all the assignments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Classification {
+    /// True under every assignment
+    Tautology,
+    /// False under every assignment
+    Contradiction,
+    /// True under some assignments and false under others
+    Satisfiable,
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Classification::Tautology => write!(f, "tautology"),
+            Classification::Contradiction => write!(f, "contradiction"),
+            Classification::Satisfiable => write!(f, "contingent"),
+        }
+    }
+}
+
+/// Classifies the formula by evaluating it under every assignment of its variables
+/// that are not given in `vars`, the given ones keep their stored values
+pub fn classify(node: &ASTNode, vars: &StoredVariables) -> Result<Classification> {
+    let free: Vec<String> = node
+        .free_variables()
+        .into_iter()
+        .filter(|var| !vars.contains_key(var))
+        .collect();
+    let (mut any_true, mut any_false) = (false, false);
+    for values in assignments(&free)? {
+        let mut assigned = vars.clone();
+        assigned.extend(free.iter().cloned().zip(values));
+        if evaluate_ref(node, &assigned)? {
+            any_true = true;
+        } else {
+            any_false = true;
+        }
     }
+    Ok(match (any_true, any_false) {
+        (true, false) => Classification::Tautology,
+        (false, true) => Classification::Contradiction,
+        _ => Classification::Satisfiable,
+    })
+}
+
+fn implication(l_value: bool, r_value: bool) -> bool {
+    !l_value || r_value
 }
 
 #[cfg(test)]
@@ -63,30 +571,379 @@ mod tests {
     use rstest::rstest;
     use std::collections::HashMap;
 
-    use crate::lexer::{Token, Value};
-    use crate::parser::construct_ast;
+    use crate::lexer::{Operator, Token, Value};
+    use crate::parser::{construct_ast, construct_ast_with, parse, ParseOptions, MAX_DEPTH};
     use crate::{lexer::Lexer, parser::ASTNode};
 
-    use super::{eval_binary, evaluate};
+    use super::{
+        classify, edit_distance, eval_binary, eval_memo, evaluate, evaluate_iter, evaluate_memo,
+        evaluate_memo_with, evaluate_overriding, evaluate_ref, evaluate_ref_with, evaluate_trace,
+        evaluate_trace_with, evaluate_with, evaluate_with_resolver, evaluate_with_resolver_with,
+        Classification, EvalOptions,
+    };
+
+    #[test]
+    fn test_evaluate_trace() {
+        let (root, vars) = construct_ast(&mut Lexer::new("1 ^ 0 v 1")).unwrap();
+
+        let (result, steps) = evaluate_trace(&root, &vars).unwrap();
+
+        assert!(result);
+        assert_eq!(
+            steps,
+            vec![
+                "1 ^ 0 = true ^ false = false",
+                "1 ^ 0 v 1 = false v true = true"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_trace_with_variables() {
+        let (root, vars) = construct_ast(&mut Lexer::new("p := 1 q := 0 ~(p ^ q)")).unwrap();
+
+        let (result, steps) = evaluate_trace(&root, &vars).unwrap();
+
+        assert!(result);
+        assert_eq!(
+            steps,
+            vec!["p ^ q = true ^ false = false", "~(p ^ q) = ~false = true"]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_trace_short_circuits() {
+        let root = parse("(0 ^ u) v (1 v (w => z))");
+
+        let (result, steps) = evaluate_trace(&root, &HashMap::new()).unwrap();
+
+        assert!(result);
+        assert_eq!(
+            steps,
+            vec![
+                "0 ^ u = false ^ u = false",
+                "1 v (w => z) = true v (w => z) = true",
+                "0 ^ u v (1 v (w => z)) = false v true = true"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_trace_depth_limit() {
+        let root = parse("~~~p");
+        let vars = HashMap::from([("p".to_string(), true)]);
+        let options = EvalOptions { max_depth: 3 };
+
+        let error = evaluate_trace_with(&root, &vars, &options).unwrap_err();
+
+        assert_eq!(error.to_string(), "Expression too deeply nested");
+        let options = EvalOptions { max_depth: 4 };
+        assert!(!evaluate_trace_with(&root, &vars, &options).unwrap().0);
+    }
+
+    #[test]
+    fn test_evaluate_with_overrides() {
+        let (root, vars) = construct_ast(&mut Lexer::new("p := 1 q := 0 p ^ ~q")).unwrap();
+        assert!(evaluate_ref(&root, &vars).unwrap());
+
+        let overrides = HashMap::from([("q".to_string(), true)]);
+
+        assert!(!evaluate_overriding(&root, &vars, &overrides).unwrap());
+        assert!(evaluate_with(&root, &overrides).is_err());
+        let all = HashMap::from([("p".to_string(), true), ("q".to_string(), false)]);
+        assert!(evaluate_with(&root, &all).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_short_circuits() {
+        assert!(!evaluate_ref(&parse("0 ^ u"), &HashMap::new()).unwrap());
+        assert!(evaluate_ref(&parse("1 v (u => w)"), &HashMap::new()).unwrap());
+        assert!(evaluate_ref(&parse("1 ^ u"), &HashMap::new()).is_err());
+
+        let mut asked = vec![];
+        let result = evaluate_with_resolver(&parse("p ^ q v r"), &mut |var| {
+            asked.push(var.to_string());
+            Some(false)
+        });
+        assert!(!result.unwrap());
+        assert_eq!(asked, vec!["p", "r"]);
+    }
+
+    #[test]
+    fn test_evaluate_memo_skips_repeated_subtrees() {
+        let options = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let parse = |expr: &str| {
+            construct_ast_with(&mut Lexer::new(expr), &options)
+                .unwrap()
+                .0
+        };
+        let root =
+            parse("(x ^ ~x) v (x => r) v ~(x => r)").substitute("x", &parse("p + (q nand r)"));
+        // None of the operators short-circuits, so every distinct subtree gets a result
+        let vars = HashMap::from([
+            ("p".to_string(), false),
+            ("q".to_string(), false),
+            ("r".to_string(), false),
+        ]);
+
+        let mut results = vec![];
+        let number = eval_memo(&root, &vars, MAX_DEPTH, &mut HashMap::new(), &mut results).unwrap();
+
+        assert_eq!(results[number], evaluate_ref(&root, &vars).unwrap());
+        assert_eq!(evaluate_memo(&root, &vars).unwrap(), results[number]);
+        assert_eq!(root.node_count(), 29);
+        assert_eq!(results.len(), 11);
+    }
+
+    #[test]
+    fn test_evaluate_memo_short_circuits() {
+        let vars = HashMap::new();
+
+        assert!(!evaluate_memo(&parse("0 ^ u"), &vars).unwrap());
+        assert!(evaluate_memo(&parse("(1 v u) ^ (w v 1 v u)"), &vars).is_err());
+        assert!(evaluate_memo(&parse("(1 v u) ^ (1 v w)"), &vars).unwrap());
+        assert_eq!(
+            evaluate_memo(&parse("u ^ 0"), &vars)
+                .unwrap_err()
+                .to_string(),
+            evaluate_ref(&parse("u ^ 0"), &vars)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_evaluate_memo_configured_depth_limit() {
+        let vars = HashMap::new();
+        let options = EvalOptions { max_depth: 2 };
+
+        assert!(evaluate_memo_with(&parse("~~1"), &vars, &options).is_err());
+        assert!(evaluate_memo_with(&parse("~1"), &vars, &options).is_ok());
+    }
+
+    #[test]
+    fn test_evaluate_depth_limit() {
+        let mut deep = ASTNode::new(Token::Value(Value::Bool(true)));
+        for _ in 0..2000 {
+            deep = deep.make_new_root_left(Token::Operator(Operator::Not));
+        }
+
+        let result = evaluate_ref(&deep, &HashMap::new());
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Expression too deeply nested"
+        );
+        assert_eq!(
+            evaluate_memo(&deep, &HashMap::new())
+                .unwrap_err()
+                .to_string(),
+            "Expression too deeply nested"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_configured_depth_limit() {
+        let root = parse("~(p ^ 1)");
+        let vars = HashMap::from([("p".to_string(), false)]);
+        let within = |max_depth| evaluate_ref_with(&root, &vars, &EvalOptions { max_depth });
+
+        assert!(within(3).unwrap());
+        assert_eq!(
+            within(2).unwrap_err().to_string(),
+            "Expression too deeply nested"
+        );
+        // The right operand of `^` is never reached when the left one is false
+        let shallow = EvalOptions { max_depth: 2 };
+        assert!(!evaluate_ref_with(&parse("0 ^ ~~~p"), &vars, &shallow).unwrap());
+    }
+
+    #[rstest]
+    #[case("1 ^ 0 v ~(1 => 0)", true)]
+    #[case("(1 <=> 0) nor ~1", true)]
+    #[case("1 nand (0 + 1)", false)]
+    fn test_evaluate_iter_matches_evaluate(#[case] expr: &str, #[case] expected: bool) {
+        let (root, vars) = construct_ast(&mut Lexer::new(expr)).unwrap();
+
+        assert_eq!(evaluate_iter(&root, &vars).unwrap(), expected);
+        assert_eq!(evaluate_ref(&root, &vars).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_evaluate_iter_short_circuits() {
+        assert!(!evaluate_iter(&parse("0 ^ u"), &HashMap::new()).unwrap());
+        assert!(evaluate_iter(&parse("1 v (u => w)"), &HashMap::new()).unwrap());
+        assert!(evaluate_iter(&parse("1 ^ u"), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_iter_deep_chain() {
+        let leaf = || ASTNode::new(Token::Value(Value::Bool(true)));
+        let mut chain = leaf();
+        for _ in 0..100_000 {
+            chain = ASTNode {
+                token: Token::Operator(Operator::And),
+                left: Some(Box::new(chain)),
+                right: Some(Box::new(leaf())),
+            };
+        }
+
+        assert!(evaluate_iter(&chain, &HashMap::new()).unwrap());
+        assert!(evaluate_ref(&chain, &HashMap::new()).is_err());
+
+        // Dropping the chain recursively would overflow the stack as well
+        let mut rest = Some(chain);
+        while let Some(mut node) = rest {
+            rest = node.left.take().map(|left| *left);
+        }
+    }
+
+    #[test]
+    fn test_undefined_variable_suggests_closest() {
+        let free = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let (root, vars) = construct_ast_with(&mut Lexer::new("p := 1  p ^ q"), &free).unwrap();
+
+        let error = evaluate_ref(&root, &vars).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Undefined variable 'q'; defined: [p]; did you mean 'p'?"
+        );
+        let error = evaluate_ref(&root, &HashMap::new()).unwrap_err();
+        assert_eq!(error.to_string(), "Undefined variable 'p'; defined: []");
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("p", "q"), 1);
+        assert_eq!(edit_distance("rain", "train"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "ab"), 2);
+    }
 
     #[test]
     fn test_eval_binary() {
         let left = Box::new(ASTNode::new(Token::Value(Value::Bool(true))));
         let right = Box::new(ASTNode::new(Token::Value(Value::Bool(false))));
 
-        let result = eval_binary(Some(left), Some(right), &HashMap::new(), |a, b| a && b).unwrap();
+        let result =
+            eval_binary(Some(left), Some(right), &HashMap::new(), 1, |a, b| a && b).unwrap();
 
-        assert_eq!(result, false);
+        assert!(!result);
     }
 
     #[test]
     fn test_eval_binary_should_handle_missing_value() {
         let left = Box::new(ASTNode::new(Token::Value(Value::Bool(true))));
-        let result = eval_binary(Some(left), None, &HashMap::new(), |a, b| a && b);
+        let result = eval_binary(Some(left), None, &HashMap::new(), 1, |a, b| a && b);
 
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_evaluate_ref_same_tree_twice() {
+        let (root, _) = construct_ast_with(
+            &mut Lexer::new("(p => q) ^ ~r"),
+            &ParseOptions {
+                free_variables: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let tree = root.clone();
+        let vars = |p, q, r| {
+            HashMap::from([
+                ("p".to_string(), p),
+                ("q".to_string(), q),
+                ("r".to_string(), r),
+            ])
+        };
+
+        assert!(evaluate_ref(&tree, &vars(false, false, false)).unwrap());
+        assert!(!evaluate_ref(&tree, &vars(true, false, false)).unwrap());
+        assert_eq!(tree, root);
+    }
+
+    #[test]
+    fn test_evaluate_with_resolver_memoizes() {
+        let mut lexer = Lexer::new("p := 1 q := 0 (p ^ q) v (p ^ ~q) v ~p");
+        let (root, _) = construct_ast(&mut lexer).unwrap();
+        let mut calls = vec![];
+        let mut resolver = |var: &str| {
+            calls.push(var.to_string());
+            Some(var == "p")
+        };
+
+        let result = evaluate_with_resolver(&root, &mut resolver).unwrap();
+
+        assert!(result);
+        assert_eq!(calls, vec!["p", "q"]);
+    }
+
+    #[test]
+    fn test_evaluate_with_resolver_should_fail_on_unresolved() {
+        let mut lexer = Lexer::new("p := 1 p ^ p");
+        let (root, _) = construct_ast(&mut lexer).unwrap();
+
+        let result = evaluate_with_resolver(&root, &mut |_| None);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_evaluate_with_resolver_depth_limit() {
+        let root = parse("~p v ~~q");
+        let within = |max_depth| {
+            evaluate_with_resolver_with(&root, &mut |_| Some(true), &EvalOptions { max_depth })
+        };
+
+        assert_eq!(
+            within(3).unwrap_err().to_string(),
+            "Expression too deeply nested"
+        );
+        assert!(within(4).unwrap());
+    }
+
+    #[rstest]
+    #[case("p v ~p", Classification::Tautology)]
+    #[case("p ^ ~p", Classification::Contradiction)]
+    #[case("p => q", Classification::Satisfiable)]
+    fn test_classify(#[case] expr: &str, #[case] expected: Classification) {
+        let result = classify(&parse(expr), &HashMap::new()).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[rstest]
+    #[case("p := 1 p v q", Classification::Tautology)]
+    #[case("p := 0 p ^ q", Classification::Contradiction)]
+    #[case("p := 1 p ^ q", Classification::Satisfiable)]
+    #[case("p := 1 q := 0 p => q", Classification::Contradiction)]
+    fn test_classify_keeps_stored_variables(#[case] expr: &str, #[case] expected: Classification) {
+        let options = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let (root, vars) = construct_ast_with(&mut Lexer::new(expr), &options).unwrap();
+
+        let result = classify(&root, &vars).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_classification_display() {
+        assert_eq!(Classification::Tautology.to_string(), "tautology");
+        assert_eq!(Classification::Contradiction.to_string(), "contradiction");
+        assert_eq!(Classification::Satisfiable.to_string(), "contingent");
+    }
+
     // Truth tables tests
     //
     // Assumption warning!
@@ -145,6 +1002,52 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[rstest]
+    #[case("1 + 1", false)]
+    #[case("1 + 0", true)]
+    #[case("0 + 1", true)]
+    #[case("0 + 0", false)]
+    #[case("1 v 1 + 1", true)]
+    #[case("1 + 1 ^ 0", true)]
+    fn test_evaluate_exclusive_disjunction(#[case] expr: &str, #[case] expected: bool) {
+        let mut lexer = Lexer::new(expr);
+        let (root, vars) = construct_ast(&mut lexer).unwrap();
+
+        let result = evaluate(root, &vars).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[rstest]
+    #[case("1 nand 1", false)]
+    #[case("1 nand 0", true)]
+    #[case("0 nand 1", true)]
+    #[case("0 nand 0", true)]
+    #[case("1 nand 1 ^ 0", false)]
+    fn test_evaluate_nand(#[case] expr: &str, #[case] expected: bool) {
+        let mut lexer = Lexer::new(expr);
+        let (root, vars) = construct_ast(&mut lexer).unwrap();
+
+        let result = evaluate(root, &vars).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[rstest]
+    #[case("1 nor 1", false)]
+    #[case("1 nor 0", false)]
+    #[case("0 nor 1", false)]
+    #[case("0 nor 0", true)]
+    #[case("1 v 0 nor 0", true)]
+    fn test_evaluate_nor(#[case] expr: &str, #[case] expected: bool) {
+        let mut lexer = Lexer::new(expr);
+        let (root, vars) = construct_ast(&mut lexer).unwrap();
+
+        let result = evaluate(root, &vars).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
     #[rstest]
     #[case("1 => 1", true)]
     #[case("1 => 0", false)]
@@ -175,12 +1078,13 @@ mod tests {
 
     #[rstest]
     #[case("1 ^ 0 v 1", true)]
-    #[case("(1 => 0) ^ 1)", false)]
+    #[case("(1 => 0) ^ 1", false)]
     #[case("~(1 ^ 1)", false)]
     #[case("~1 v ~1 <=> 0", true)]
     #[case("~1 v ~0 <=> ~(1 ^ 0)", true)]
     #[case("((1 v 0) => 0) ^ 1", false)]
     #[case("p := 1 q := 0 r := 1 p ^ q ^ r", false)]
+    #[case("0 => 1 => 0", true)]
     fn test_evaluate_complex_expressions(#[case] expr: &str, #[case] expected: bool) {
         let mut lexer = Lexer::new(expr);
         let (root, vars) = construct_ast(&mut lexer).unwrap();
@@ -2,21 +2,74 @@ use std::{fmt, iter::Peekable, str::Chars};
 
 use anyhow::{anyhow, Result};
 
-// Order of variants in this enum encodes operator precedence
-// where top one is the least significant
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+// Order of variants in this enum encodes the default operator precedence
+// where top one is the least significant, see `parser::Precedence` for changing it.
+// NOR binds just tighter than OR and NAND just tighter than AND,
+// so `p v q nor r` is `p v (q nor r)` and `p nand q ^ r` is `(p nand q) ^ r`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operator {
     Equivalence,
     Implication,
     Or,
+    Nor,
+    Xor,
     And,
+    Nand,
     Not,
     ParenthisClosed,
     ParenthisOpen,
     Assign,
+    /// Ends a statement of a program, see `parse_program`
+    Separator,
 }
 
-#[derive(Debug, PartialEq)]
+impl Operator {
+    /// Infix connectives taking both left and right operand
+    pub fn is_binary(&self) -> bool {
+        matches!(
+            self,
+            Operator::Equivalence
+                | Operator::Implication
+                | Operator::Or
+                | Operator::Nor
+                | Operator::Xor
+                | Operator::And
+                | Operator::Nand
+        )
+    }
+
+    /// Infix connectives whose operands can be swapped
+    pub fn is_commutative(&self) -> bool {
+        self.is_binary() && *self != Operator::Implication
+    }
+
+    /// Chains of the operator group to the right, so `a => b => c` is `a => (b => c)`
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, Operator::Implication)
+    }
+
+    /// Notation of the operator in the source
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Equivalence => "<=>",
+            Operator::Implication => "=>",
+            Operator::Or => "v",
+            Operator::Nor => "nor",
+            Operator::Xor => "+",
+            Operator::And => "^",
+            Operator::Nand => "nand",
+            Operator::Not => "~",
+            Operator::ParenthisClosed => ")",
+            Operator::ParenthisOpen => "(",
+            Operator::Assign => ":=",
+            Operator::Separator => ";",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     Bool(bool),
     Variable(String),
@@ -31,7 +84,8 @@ impl fmt::Display for Value {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
     Value(Value),
     Operator(Operator),
@@ -43,11 +97,7 @@ impl Token {
     }
 
     fn eval_bool(ch: char) -> bool {
-        if ch == '0' {
-            false
-        } else {
-            true
-        }
+        ch != '0'
     }
 }
 
@@ -60,85 +110,271 @@ impl fmt::Display for Token {
     }
 }
 
+/// Operators and constants spelled as words. A word is a keyword only when the whole run
+/// of letters matches, otherwise its letters are read as variables.
+/// This makes `T` and `F` on their own constants, so they can't be used as variable names.
+/// The longer keywords match in any case, `AND` and `And` as well as `and`;
+/// `T` and `F` only in upper case, leaving `t` and `f` for variables.
+const KEYWORDS: [(&str, Token); 10] = [
+    ("and", Token::Operator(Operator::And)),
+    ("or", Token::Operator(Operator::Or)),
+    ("not", Token::Operator(Operator::Not)),
+    ("nand", Token::Operator(Operator::Nand)),
+    ("nor", Token::Operator(Operator::Nor)),
+    ("xor", Token::Operator(Operator::Xor)),
+    ("true", Token::Value(Value::Bool(true))),
+    ("false", Token::Value(Value::Bool(false))),
+    ("T", Token::Value(Value::Bool(true))),
+    ("F", Token::Value(Value::Bool(false))),
+];
+
+/// Position in the source, counting characters. Lines and columns start at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Span {
+    fn default() -> Span {
+        Span {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Value along with where it starts in the source
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+#[derive(Clone)]
 pub struct Lexer<'a> {
     chars: Peekable<Chars<'a>>,
+    /// Position of the next character
+    position: Span,
 }
 
-impl Lexer<'_> {
-    pub fn new(contents: &str) -> Lexer {
+impl<'a> Lexer<'a> {
+    pub fn new(contents: &str) -> Lexer<'_> {
         Lexer {
             chars: contents.chars().peekable(),
+            position: Span::default(),
+        }
+    }
+
+    /// The tokens alone, without their positions
+    pub fn strip_spans(self) -> impl Iterator<Item = Result<Token>> + 'a {
+        self.map(|token| token.map(|spanned| spanned.value))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.chars.next();
+        match ch {
+            Some('\n') => {
+                self.position.line += 1;
+                self.position.column = 1;
+            }
+            Some(_) => self.position.column += 1,
+            None => return None,
+        }
+        self.position.offset += 1;
+        ch
+    }
+
+    /// Consumes the next character only if it's the expected one
+    fn bump_if(&mut self, expected: char) -> bool {
+        if self.chars.peek() == Some(&expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes the rest of the comment, leaving the newline to be read as whitespace
+    fn skip_line(&mut self) {
+        while self.chars.next_if(|ch| *ch != '\n').is_some() {
+            self.position.column += 1;
+            self.position.offset += 1;
         }
     }
+
+    /// Consumes the rest of the keyword starting with the given letter, if it is one
+    fn keyword(&mut self, first: char) -> Option<Token> {
+        let mut word = first.to_string();
+        let mut ahead = self.chars.clone();
+        while let Some(ch) = ahead.next_if(|ch| ch.is_ascii_alphabetic()) {
+            word.push(ch);
+        }
+        let (_, token) = KEYWORDS.iter().find(|(keyword, _)| {
+            *keyword == word || (keyword.len() > 1 && keyword.eq_ignore_ascii_case(&word))
+        })?;
+        for _ in 1..word.len() {
+            self.bump();
+        }
+        Some(token.clone())
+    }
 }
 
 impl Iterator for Lexer<'_> {
-    type Item = Result<Token>;
+    type Item = Result<Spanned<Token>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let ch = self.chars.next();
+            let start = self.position;
+            let ch = self.bump();
             let token = match ch {
-                Some('^') => Token::Operator(Operator::And),
-                Some('v') => Token::Operator(Operator::Or),
-                Some('~') => Token::Operator(Operator::Not),
+                Some('^') | Some('∧') => Token::Operator(Operator::And),
+                Some('v') | Some('∨') => Token::Operator(Operator::Or),
+                Some('+') | Some('⊕') => Token::Operator(Operator::Xor),
+                Some('~') | Some('¬') => Token::Operator(Operator::Not),
+                Some('&') => {
+                    // "&&" is read the same as a single "&"
+                    self.bump_if('&');
+                    Token::Operator(Operator::And)
+                }
+                Some('|') => {
+                    self.bump_if('|');
+                    Token::Operator(Operator::Or)
+                }
+                Some('!') => Token::Operator(Operator::Not),
+                Some('-') => {
+                    if self.bump_if('>') {
+                        Token::Operator(Operator::Implication)
+                    } else {
+                        return Some(Err(anyhow!(
+                            "Unexpected '-' at {}. Did you mean '->'?",
+                            start
+                        )));
+                    }
+                }
+                Some('#') => {
+                    self.skip_line();
+                    continue;
+                }
+                Some('/') => {
+                    if self.bump_if('/') {
+                        self.skip_line();
+                        continue;
+                    } else {
+                        return Some(Err(anyhow!(
+                            "Unexpected '/' at {}. Did you mean '//' comment?",
+                            start
+                        )));
+                    }
+                }
+                Some('↑') => Token::Operator(Operator::Nand),
+                Some('↓') => Token::Operator(Operator::Nor),
+                Some('→') => Token::Operator(Operator::Implication),
+                Some('↔') => Token::Operator(Operator::Equivalence),
+                Some(';') => Token::Operator(Operator::Separator),
                 Some('(') => Token::Operator(Operator::ParenthisOpen),
                 Some(')') => Token::Operator(Operator::ParenthisClosed),
                 Some('<') => {
-                    // "<=>" equivalence
-                    let next = self.chars.next();
-                    let next_after = self.chars.next();
-                    if let (Some('='), Some('>')) = (next, next_after) {
+                    // "<=>" or "<->" equivalence. Only the characters that fit are consumed,
+                    // so the ones that don't are read again as the following tokens.
+                    let middle = ['=', '-'].into_iter().find(|ch| self.bump_if(*ch));
+                    if middle.is_some() && self.bump_if('>') {
                         Token::Operator(Operator::Equivalence)
                     } else {
+                        let found: String = ['<'].into_iter().chain(middle).collect();
                         return Some(Err(anyhow!(
-                            "Unexpected '{}{}' after <. Did you mean '<=>'?",
-                            next.unwrap_or(' '),
-                            next_after.unwrap_or(' ')
+                            "Unexpected '{}' followed by {} at {}. Did you mean '<=>'?",
+                            found,
+                            match self.chars.peek() {
+                                Some(ch) => format!("'{}'", ch),
+                                None => String::from("the end of input"),
+                            },
+                            start
                         )));
                     }
                 }
                 Some(':') => {
-                    let next = self.chars.peek();
-                    if let Some('=') = next {
-                        self.chars.next();
+                    if self.bump_if('=') {
                         Token::Operator(Operator::Assign)
                     } else {
-                        continue;
+                        return Some(Err(anyhow!(
+                            "Unexpected ':' at {}. Did you mean ':=' (assignment)?",
+                            start
+                        )));
                     }
                 }
                 Some('=') => {
-                    // "=>" implication is differentiated from equivalence only because
-                    // the iterator advanced twice on previous step
-                    let next = self.chars.peek();
-                    if let Some('>') = next {
-                        self.chars.next();
+                    if self.bump_if('>') {
                         Token::Operator(Operator::Implication)
                     } else {
-                        continue;
+                        return Some(Err(anyhow!(
+                            "Unexpected '=' at {}. Did you mean '=>' (implication) or '<=>' (equivalence)?",
+                            start
+                        )));
+                    }
+                }
+                Some('?') => match self.bump() {
+                    // Metavariable of a rewrite pattern
+                    Some(name) if name.is_ascii_alphabetic() => {
+                        Token::Value(Value::Variable(format!("?{}", name)))
                     }
+                    other => {
+                        return Some(Err(anyhow!(
+                            "Expected name of pattern variable after '?' at {}, got '{}'",
+                            start,
+                            other.unwrap_or(' ')
+                        )))
+                    }
+                },
+                Some(digit @ ('0' | '1')) => Token::from_digit(digit),
+                Some(other) if other.is_ascii_digit() => {
+                    return Some(Err(anyhow!(
+                        "Unexpected digit '{}' at {}, expected 0 or 1",
+                        other,
+                        start
+                    )))
                 }
-                Some(other) if other.is_digit(10) => Token::from_digit(other),
                 Some(other) if other.is_whitespace() => continue,
-                Some(other) if other.is_ascii_alphabetic() => {
-                    Token::Value(Value::Variable(other.to_string()))
+                Some(other) if other.is_ascii_alphabetic() => self
+                    .keyword(other)
+                    .unwrap_or_else(|| Token::Value(Value::Variable(other.to_string()))),
+                Some(other) => {
+                    return Some(Err(anyhow!(
+                        "Unexpected character '{}' at {}",
+                        other,
+                        start
+                    )))
                 }
-                Some(other) => return Some(Err(anyhow!("Unexpected character '{}'", other))),
                 None => return None,
             };
-            return Some(Ok(token));
+            return Some(Ok(Spanned {
+                value: token,
+                span: start,
+            }));
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Lexer, Operator, Token, Value};
+    use anyhow::Result;
+
+    use super::{Lexer, Operator, Spanned, Token, Value};
 
     #[test]
     fn test_lexer_simple() {
         let lexer = Lexer::new("1 ^ 0 v ~1 => 0 <=> 1");
-        let result: Vec<Token> = lexer.into_iter().map(|r| r.unwrap()).collect();
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
 
         let expected = vec![
             Token::Value(Value::Bool(true)),
@@ -158,7 +394,7 @@ mod tests {
     #[test]
     fn test_lexer_parents() {
         let lexer = Lexer::new("(1 ^ 0) ^ 1");
-        let result: Vec<Token> = lexer.into_iter().map(|r| r.unwrap()).collect();
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
 
         let expected = vec![
             Token::Operator(Operator::ParenthisOpen),
@@ -175,7 +411,7 @@ mod tests {
     #[test]
     fn test_lexer_variables() {
         let lexer = Lexer::new("p := 1 q := 0 p ^ q");
-        let result: Vec<Token> = lexer.into_iter().map(|r| r.unwrap()).collect();
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
 
         let expected = vec![
             Token::Value(Value::Variable("p".to_string())),
@@ -190,4 +426,270 @@ mod tests {
         ];
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_lexer_xor() {
+        let lexer = Lexer::new("1 + 0");
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
+
+        let expected = vec![
+            Token::Value(Value::Bool(true)),
+            Token::Operator(Operator::Xor),
+            Token::Value(Value::Bool(false)),
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_lexer_keywords() {
+        let lexer = Lexer::new("p nand q nor (r xor s)");
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
+
+        let expected = vec![
+            Token::Value(Value::Variable("p".to_string())),
+            Token::Operator(Operator::Nand),
+            Token::Value(Value::Variable("q".to_string())),
+            Token::Operator(Operator::Nor),
+            Token::Operator(Operator::ParenthisOpen),
+            Token::Value(Value::Variable("r".to_string())),
+            Token::Operator(Operator::Xor),
+            Token::Value(Value::Variable("s".to_string())),
+            Token::Operator(Operator::ParenthisClosed),
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_lexer_keyword_prefix_is_variables() {
+        let lexer = Lexer::new("no nors");
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
+
+        let expected: Vec<Token> = "nonors"
+            .chars()
+            .map(|c| Token::Value(Value::Variable(c.to_string())))
+            .collect();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_lexer_constant_keywords() {
+        let lexer = Lexer::new("T ^ F v true + false");
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
+
+        let expected = vec![
+            Token::Value(Value::Bool(true)),
+            Token::Operator(Operator::And),
+            Token::Value(Value::Bool(false)),
+            Token::Operator(Operator::Or),
+            Token::Value(Value::Bool(true)),
+            Token::Operator(Operator::Xor),
+            Token::Value(Value::Bool(false)),
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_lexer_lowercase_t_is_variable() {
+        let lexer = Lexer::new("t ^ f");
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
+
+        assert_eq!(result[0], Token::Value(Value::Variable("t".to_string())));
+        assert_eq!(result[2], Token::Value(Value::Variable("f".to_string())));
+    }
+
+    #[test]
+    fn test_lexer_skips_comments() {
+        let lexer = Lexer::new("# weather\np := 1  # it rained\n// wet\n~p // not\n");
+        let result: Vec<Spanned<Token>> = lexer.map(|r| r.unwrap()).collect();
+
+        let tokens: Vec<Token> = result.iter().map(|t| t.value.clone()).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Value(Value::Variable("p".to_string())),
+                Token::Operator(Operator::Assign),
+                Token::Value(Value::Bool(true)),
+                Token::Operator(Operator::Not),
+                Token::Value(Value::Variable("p".to_string())),
+            ]
+        );
+        assert_eq!(result[3].span.to_string(), "4:1");
+    }
+
+    #[test]
+    fn test_lexer_lone_slash() {
+        let result: Vec<Result<Token>> = Lexer::new("p / q").strip_spans().collect();
+
+        assert!(result[1].is_err());
+    }
+
+    #[test]
+    fn test_lexer_spans() {
+        let lexer = Lexer::new("p := 1\n  ~p <=> nor");
+        let spans: Vec<String> = lexer.map(|r| r.unwrap().span.to_string()).collect();
+
+        assert_eq!(
+            spans,
+            vec!["1:1", "1:3", "1:6", "2:3", "2:4", "2:6", "2:10"]
+        );
+    }
+
+    #[test]
+    fn test_lexer_unexpected_character_position() {
+        let mut lexer = Lexer::new("p ^\n q @ r");
+        lexer.next();
+        lexer.next();
+        lexer.next();
+
+        let error = lexer.next().unwrap().unwrap_err();
+
+        assert_eq!(error.to_string(), "Unexpected character '@' at 2:4");
+    }
+
+    #[test]
+    fn test_lexer_unicode_operators() {
+        let lexer = Lexer::new("¬p ∧ q v r → (p ↔ ~q) ∨ p ⊕ q ↑ r ↓ p");
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
+
+        let variable = |name: &str| Token::Value(Value::Variable(name.to_string()));
+        let expected = vec![
+            Token::Operator(Operator::Not),
+            variable("p"),
+            Token::Operator(Operator::And),
+            variable("q"),
+            Token::Operator(Operator::Or),
+            variable("r"),
+            Token::Operator(Operator::Implication),
+            Token::Operator(Operator::ParenthisOpen),
+            variable("p"),
+            Token::Operator(Operator::Equivalence),
+            Token::Operator(Operator::Not),
+            variable("q"),
+            Token::Operator(Operator::ParenthisClosed),
+            Token::Operator(Operator::Or),
+            variable("p"),
+            Token::Operator(Operator::Xor),
+            variable("q"),
+            Token::Operator(Operator::Nand),
+            variable("r"),
+            Token::Operator(Operator::Nor),
+            variable("p"),
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_lexer_c_style_aliases() {
+        let tokens =
+            |expr| -> Vec<Token> { Lexer::new(expr).strip_spans().map(|r| r.unwrap()).collect() };
+
+        assert_eq!(tokens("1 && 0 || !1"), tokens("1 ^ 0 v ~1"));
+        assert_eq!(tokens("1 & 0 | !!1"), tokens("1 ^ 0 v ~~1"));
+        assert_eq!(tokens("1 -> 0 <-> 1 => 0"), tokens("1 => 0 <=> 1 => 0"));
+    }
+
+    #[test]
+    fn test_lexer_lone_dash() {
+        let mut lexer = Lexer::new("1 - 0");
+        lexer.next();
+
+        assert!(lexer.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_lexer_pattern_variables() {
+        let lexer = Lexer::new("?x ^ p");
+        let result: Vec<Token> = lexer.strip_spans().map(|r| r.unwrap()).collect();
+
+        let expected = vec![
+            Token::Value(Value::Variable("?x".to_string())),
+            Token::Operator(Operator::And),
+            Token::Value(Value::Variable("p".to_string())),
+        ];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_lexer_pattern_variable_without_name() {
+        let mut lexer = Lexer::new("? ^ p");
+
+        assert!(lexer.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_lexer_lone_equals_sign() {
+        let result: Vec<Result<Token>> = Lexer::new("1 = 0").strip_spans().collect();
+
+        assert_eq!(
+            result[1].as_ref().unwrap_err().to_string(),
+            "Unexpected '=' at 1:3. Did you mean '=>' (implication) or '<=>' (equivalence)?"
+        );
+    }
+
+    #[test]
+    fn test_lexer_lone_colon() {
+        let result: Vec<Result<Token>> = Lexer::new("p : 1").strip_spans().collect();
+
+        assert_eq!(
+            result[1].as_ref().unwrap_err().to_string(),
+            "Unexpected ':' at 1:3. Did you mean ':=' (assignment)?"
+        );
+    }
+
+    #[test]
+    fn test_lexer_incomplete_equivalence_keeps_following_tokens() {
+        for (expr, error) in [
+            (
+                "1 < 0",
+                "Unexpected '<' followed by ' ' at 1:3. Did you mean '<=>'?",
+            ),
+            (
+                "1 <= 0",
+                "Unexpected '<=' followed by ' ' at 1:3. Did you mean '<=>'?",
+            ),
+            (
+                "1 <0",
+                "Unexpected '<' followed by '0' at 1:3. Did you mean '<=>'?",
+            ),
+        ] {
+            let result: Vec<Result<Token>> = Lexer::new(expr).strip_spans().collect();
+
+            assert_eq!(result[1].as_ref().unwrap_err().to_string(), error);
+            assert_eq!(
+                result[2].as_ref().unwrap(),
+                &Token::Value(Value::Bool(false))
+            );
+        }
+    }
+
+    #[test]
+    fn test_lexer_non_binary_digit() {
+        let result: Vec<Result<Token>> = Lexer::new("1 ^ 2").strip_spans().collect();
+
+        assert_eq!(
+            result[2].as_ref().unwrap_err().to_string(),
+            "Unexpected digit '2' at 1:5, expected 0 or 1"
+        );
+    }
+
+    #[test]
+    fn test_lexer_keywords_in_any_case() {
+        let tokens =
+            |expr| -> Vec<Token> { Lexer::new(expr).strip_spans().map(|r| r.unwrap()).collect() };
+
+        assert_eq!(tokens("1 AND 0"), tokens("1 ^ 0"));
+        assert_eq!(tokens("1 aNd 0"), tokens("1 ^ 0"));
+        assert_eq!(tokens("NOT p Or q XOR True"), tokens("~p v q + 1"));
+        assert_eq!(tokens("t ^ f"), tokens("t and f"));
+        assert_ne!(tokens("t"), tokens("T"));
+        // Keywords are reserved in any case, so this is an operator followed by `:=`
+        assert_eq!(
+            tokens("And := 1"),
+            vec![
+                Token::Operator(Operator::And),
+                Token::Operator(Operator::Assign),
+                Token::Value(Value::Bool(true)),
+            ]
+        );
+    }
 }
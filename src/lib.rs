@@ -1,3 +1,228 @@
+pub mod export;
+pub mod interpreter;
 pub mod lexer;
+pub mod minimize;
 pub mod parser;
-pub mod interpreter;
+pub mod rewrite;
+pub mod simplify;
+pub mod solver;
+pub mod synthesis;
+pub mod table;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use anyhow::Result;
+
+use interpreter::evaluate;
+use lexer::{Lexer, Operator, Spanned, Token, Value};
+use parser::{
+    construct_ast, construct_ast_with, construct_program, ASTNode, ParseOptions, Program,
+};
+pub use parser::{stored_variables, StoredVariables};
+
+/// Parses the statement into its tree and the variables assigned with `:=`.
+/// Like in the binary, variables don't have to be assigned and are left free then:
+///
+/// ```
+/// let (root, variables) = logic_solver::parse_expr("p := 1 p ^ q").unwrap();
+///
+/// assert_eq!(root.to_expr_string(), "p ^ q");
+/// assert_eq!(variables.get("p"), Some(&true));
+/// assert_eq!(variables.get("q"), None);
+/// ```
+pub fn parse_expr(contents: &str) -> Result<(ASTNode, StoredVariables)> {
+    let options = ParseOptions {
+        free_variables: true,
+        ..Default::default()
+    };
+    construct_ast_with(&mut Lexer::new(contents), &options)
+}
+
+/// Same as `parse_expr` for a program of statements separated with `;`, parsed with
+/// the given options. Variables are left free whatever `free_variables` says, e.g.
+///
+/// ```
+/// let options = logic_solver::parser::ParseOptions::default();
+/// let program = logic_solver::parse_program_expr("p := 1 q := p; p ^ r", &options).unwrap();
+///
+/// assert_eq!(program.statements[0].0.to_expr_string(), "p ^ r");
+/// assert!(program.assignment_reads.contains("p"));
+/// ```
+pub fn parse_program_expr(contents: &str, options: &ParseOptions) -> Result<Program> {
+    let options = ParseOptions {
+        free_variables: true,
+        ..options.clone()
+    };
+    construct_program(contents, &options)
+}
+
+/// Parses and evaluates the statement in one go. Every variable has to be assigned
+/// in the statement itself, e.g.
+///
+/// ```
+/// assert!(logic_solver::eval_str("1 ^ 0 v 1").unwrap());
+/// assert!(!logic_solver::eval_str("p := 1 q := 0 p ^ q").unwrap());
+/// ```
+pub fn eval_str(expr: &str) -> Result<bool> {
+    let (root, variables) = construct_ast(&mut Lexer::new(expr))?;
+    evaluate(root, &variables)
+}
+
+/// Cheap syntactic check of the statement: balanced parentheses, operands where
+/// the operators need them and assignments only before the expression.
+/// Unlike `parse_expr` it builds no tree and doesn't look at the variables, e.g.
+///
+/// ```
+/// assert!(logic_solver::is_well_formed("p := 1 ~(p ^ q)"));
+/// assert!(!logic_solver::is_well_formed("(p ^) q"));
+/// ```
+pub fn is_well_formed(expr: &str) -> bool {
+    let mut tokens = Lexer::new(expr).peekable();
+    let mut depth = 0;
+    let mut after_operand = false;
+    // Reading the expression right of `:=`, the statement itself hasn't started yet
+    let mut in_assignment = false;
+    let mut body_started = false;
+
+    while let Some(token) = tokens.next() {
+        let Ok(Spanned { value: token, .. }) = token else {
+            return false;
+        };
+        let assign_follows = matches!(
+            tokens.peek(),
+            Some(Ok(Spanned {
+                value: Token::Operator(Operator::Assign),
+                ..
+            }))
+        );
+        match token {
+            Token::Value(Value::Variable(_)) if assign_follows => {
+                let assignment_done = !in_assignment || after_operand;
+                if depth > 0 || body_started || !assignment_done {
+                    return false;
+                }
+                tokens.next();
+                in_assignment = true;
+                after_operand = false;
+            }
+            Token::Value(_)
+            | Token::Operator(Operator::Not)
+            | Token::Operator(Operator::ParenthisOpen) => {
+                if after_operand {
+                    // An operand right after a complete assigned expression starts the next part
+                    if !in_assignment || depth > 0 {
+                        return false;
+                    }
+                    in_assignment = false;
+                }
+                body_started |= !in_assignment;
+                after_operand = matches!(token, Token::Value(_));
+                if token == Token::Operator(Operator::ParenthisOpen) {
+                    depth += 1;
+                }
+            }
+            Token::Operator(Operator::ParenthisClosed) => {
+                if depth == 0 || !after_operand {
+                    return false;
+                }
+                depth -= 1;
+            }
+            Token::Operator(op) if op.is_binary() => {
+                if !after_operand {
+                    return false;
+                }
+                after_operand = false;
+            }
+            Token::Operator(_) => return false,
+        }
+    }
+    after_operand && depth == 0 && !in_assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::{eval_str, is_well_formed, parse_expr, parse_program_expr, StoredVariables};
+    use crate::parser::ParseOptions;
+
+    #[test]
+    fn test_eval_str() {
+        assert!(eval_str("1 ^ 0 v 1").unwrap());
+        assert!(eval_str("p := 1 ~p => 1 ^ p").unwrap());
+    }
+
+    #[test]
+    fn test_eval_str_should_fail_on_unassigned_variable() {
+        let result = eval_str("p ^ 1");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Undefined variable 'p' at 1:1; defined: []"
+        );
+    }
+
+    #[test]
+    fn test_eval_str_suggests_defined_variable() {
+        let result = eval_str("p := 1 p ^ ~q");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Undefined variable 'q' at 1:13; defined: [p]; did you mean 'p'?"
+        );
+    }
+
+    #[test]
+    fn test_parse_expr_leaves_variables_free() {
+        let (root, variables) = parse_expr("q := 0 ~(p v q)").unwrap();
+
+        assert_eq!(root.to_expr_string(), "~(p v q)");
+        assert_eq!(variables, StoredVariables::from([("q".to_string(), false)]));
+        assert!(parse_expr("p ^").is_err());
+    }
+
+    #[test]
+    fn test_parse_program_expr_applies_options() {
+        let options = ParseOptions {
+            strict: true,
+            overrides: StoredVariables::from([("q".to_string(), true)]),
+            ..Default::default()
+        };
+
+        let program = parse_program_expr("p := q; ~p v r", &options).unwrap();
+
+        assert_eq!(program.statements.len(), 1);
+        assert_eq!(program.statements[0].1.get("p"), Some(&true));
+        assert!(parse_program_expr("p => q <=> r", &options).is_err());
+    }
+
+    #[rstest]
+    #[case("1 ^ 0 v 1")]
+    #[case("~(p ^ q) => r")]
+    #[case("((p)) <=> ~~q")]
+    #[case("p := 1 q := p ^ 0 p v q")]
+    #[case("p := 1 ^ 0  (p v 0)")]
+    fn test_is_well_formed(#[case] expr: &str) {
+        assert!(is_well_formed(expr));
+        assert!(parse_expr(expr).is_ok());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("p ^")]
+    #[case("^ p")]
+    #[case("(p v q")]
+    #[case("p v q)")]
+    #[case("()")]
+    #[case("~")]
+    #[case("p q")]
+    #[case("p := 1")]
+    #[case("p := q := 1 p")]
+    #[case("1 ^ (p := 0)")]
+    #[case("p := 1; p")]
+    #[case("p = q")]
+    fn test_is_not_well_formed(#[case] expr: &str) {
+        assert!(!is_well_formed(expr));
+        assert!(parse_expr(expr).is_err());
+    }
+}
@@ -1,40 +1,489 @@
 use anyhow::{anyhow, Result};
-use std::fs::File;
-use std::io::Read;
+use std::collections::HashMap;
 use std::env;
+use std::fs::File;
+use std::io::{self, Read};
 use std::path::Path;
+use std::process;
 
 use env_logger::Env;
 
-use logic_solver::parser::{ASTNode, StoredVariables, construct_ast};
-use logic_solver::lexer::Lexer;
-use logic_solver::interpreter::evaluate;
+use logic_solver::export::{to_dimacs, to_latex};
+use logic_solver::interpreter::{
+    classify, evaluate_ref, evaluate_trace, evaluate_with, project, Classification,
+};
+use logic_solver::lexer::{Lexer, Spanned, Token, Value};
+use logic_solver::minimize::minimize_sop;
+use logic_solver::parse_program_expr;
+use logic_solver::parser::{ASTNode, ImageFormat, ParseOptions, Program, StoredVariables};
+use logic_solver::table::{truth_table, truth_table_csv, TableFormat};
+
+/// Parses the statements with `parse_program_expr`, leaving unassigned variables free
+fn parse(contents: &str, strict: bool, overrides: StoredVariables) -> Result<Program> {
+    let options = ParseOptions {
+        strict,
+        overrides,
+        ..Default::default()
+    };
+    parse_program_expr(contents, &options)
+}
+
+/// Reads the `name=value` of an `--assign` option, the value being 1, 0, true or false
+fn parse_assignment(arg: &str) -> Result<(String, bool)> {
+    let Some((name, value)) = arg.split_once('=') else {
+        return Err(anyhow!("Expected name=value after --assign, got '{}'", arg));
+    };
+    let tokens: Vec<_> = Lexer::new(name).collect();
+    let is_variable = matches!(
+        tokens.as_slice(),
+        [Ok(Spanned { value: Token::Value(Value::Variable(var)), .. })] if var == name
+    );
+    if !is_variable {
+        return Err(anyhow!(
+            "Invalid variable name '{}' in --assign {}",
+            name,
+            arg
+        ));
+    }
+    let value = match value {
+        "1" | "true" => true,
+        "0" | "false" => false,
+        other => {
+            return Err(anyhow!(
+                "Invalid value '{}' in --assign {}, expected 1, 0, true or false",
+                other,
+                arg
+            ))
+        }
+    };
+    Ok((name.to_string(), value))
+}
+
+fn has_unassigned(root: &ASTNode, variables: &StoredVariables) -> bool {
+    root.free_variables()
+        .iter()
+        .any(|var| !variables.contains_key(var))
+}
+
+/// Variables assigned in the program that neither its statements nor the expressions
+/// assigned to other variables read, in alphabetical order
+fn unused_variables(program: &Program) -> Vec<String> {
+    let Some((_, variables)) = program.statements.last() else {
+        return vec![];
+    };
+    let mut used = program.assignment_reads.clone();
+    for (root, _) in &program.statements {
+        used.extend(root.free_variables());
+    }
+    let mut unused: Vec<String> = variables
+        .keys()
+        .filter(|var| !used.contains(*var))
+        .cloned()
+        .collect();
+    unused.sort();
+    unused
+}
+
+/// Formula with unassigned variables has no single result,
+/// so it's described by its simplified form and classification instead
+fn describe(root: &ASTNode, variables: &StoredVariables) -> Result<String> {
+    let simplified = project(root, variables)?.simplify()?;
+    let classification = classify(root, variables)?;
+    Ok(format!(
+        "Simplified: {}\nClassification: {}",
+        simplified.to_expr_string(),
+        classification
+    ))
+}
 
-fn parse(contents: &str) -> Result<(ASTNode, StoredVariables)> {
-    let mut lexer = Lexer::new(contents);
-    let (root, variables) = construct_ast(&mut lexer)?;
-    Ok((root, variables))
+/// Verdict of the `check` subcommand and whether the formula is valid. Without unassigned
+/// variables there is nothing to enumerate, so the formula is simply evaluated.
+fn check(root: &ASTNode, variables: &StoredVariables) -> Result<(String, bool)> {
+    if !has_unassigned(root, variables) {
+        let res = evaluate_ref(root, variables)?;
+        return Ok((format!("Result: {}", res), res));
+    }
+    let classification = classify(root, variables)?;
+    Ok((
+        format!("Classification: {}", classification),
+        classification == Classification::Tautology,
+    ))
+}
+
+#[cfg(feature = "serde")]
+fn print_json(root: &ASTNode) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(root)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_json(_root: &ASTNode) -> Result<()> {
+    Err(anyhow!(
+        "Option --json needs the serde feature, build with --features serde"
+    ))
+}
+
+/// Writes the graph in the dot format, or renders it when the path has an image extension.
+/// The result is only added to the label of the root in the dot format.
+fn write_graph(root: &ASTNode, path: &Path, result: Option<bool>) -> Result<()> {
+    if path.extension().is_some_and(|ext| ext != "dot") {
+        return root.render_image(path, ImageFormat::from_path(path)?);
+    }
+    match result {
+        Some(result) => root.visualize_graph_with_result(path, result),
+        None => root.visualize_graph(path),
+    }
+}
+
+/// What is printed for the statement instead of its result
+enum Output {
+    Table,
+    Csv,
+    Dimacs,
+    Json,
+    Minimize,
+    Stats,
+    Latex,
+    Tree,
+}
+
+impl Output {
+    fn from_flag(flag: &str) -> Option<Output> {
+        match flag {
+            "--table" => Some(Output::Table),
+            "--csv" => Some(Output::Csv),
+            "--dimacs" => Some(Output::Dimacs),
+            "--json" => Some(Output::Json),
+            "--minimize" => Some(Output::Minimize),
+            "--stats" => Some(Output::Stats),
+            "--latex" => Some(Output::Latex),
+            "--tree" => Some(Output::Tree),
+            _ => None,
+        }
+    }
+
+    /// Prints the statement with the assigned variables substituted and folded,
+    /// so that every output is over the unassigned variables alone
+    fn print(&self, root: &ASTNode, variables: &StoredVariables) -> Result<()> {
+        let root = project(root, variables)?;
+        match self {
+            Output::Table => truth_table(&root)?.write(&mut io::stdout(), TableFormat::Ascii)?,
+            Output::Csv => print!("{}", truth_table_csv(&root)?),
+            Output::Dimacs => print!("{}", to_dimacs(&root)?),
+            Output::Json => print_json(&root)?,
+            Output::Minimize => println!("{}", minimize_sop(&root)?.to_expr_string()),
+            Output::Stats => {
+                println!("Depth: {}", root.depth());
+                println!("Nodes: {}", root.node_count());
+                println!("Operators: {}", root.operator_count());
+            }
+            Output::Latex => println!("{}", to_latex(&root)),
+            Output::Tree => print!("{}", root.to_tree_string()),
+        }
+        Ok(())
+    }
+}
+
+/// Where the statement is read from
+enum Source {
+    File(String),
+    Inline(String),
+    Stdin,
+}
+
+impl Source {
+    fn read(self) -> Result<String> {
+        let mut buffer = String::new();
+        match self {
+            Source::File(path) => {
+                let mut file = match File::open(&path) {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        return Err(anyhow!("File not found: {}", path))
+                    }
+                    Err(e) => return Err(anyhow!("Cannot open file {}: {}", path, e)),
+                };
+                file.read_to_string(&mut buffer)?;
+            }
+            Source::Inline(expression) => buffer = expression,
+            Source::Stdin => {
+                io::stdin().read_to_string(&mut buffer)?;
+            }
+        }
+        Ok(buffer)
+    }
 }
 
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let file_path = match args.len() {
-        1 => return Err(anyhow!("Please provide file path to the statement")),
-        2 => &args[1],
-        _ => return Err(anyhow!("Expected just one file path")),
+    let mut source = None;
+    let mut dot_with_result = false;
+    let mut output = None;
+    let mut trace = false;
+    let mut exit_code = false;
+    let mut quiet = false;
+    let mut strict = false;
+    let mut graph = None;
+    let mut overrides = HashMap::new();
+    let mut args = env::args().skip(1).peekable();
+    let check_only = args.next_if(|arg| arg == "check").is_some();
+    while let Some(arg) = args.next() {
+        let next_source = match arg.as_str() {
+            "--output-dot-and-result" => {
+                dot_with_result = true;
+                continue;
+            }
+            "--trace" => {
+                trace = true;
+                continue;
+            }
+            "--exit-code" => {
+                exit_code = true;
+                continue;
+            }
+            "--quiet" => {
+                quiet = true;
+                continue;
+            }
+            "--strict" => {
+                strict = true;
+                continue;
+            }
+            "--graph" => {
+                match args.next() {
+                    Some(path) => graph = Some(path),
+                    None => return Err(anyhow!("Expected an output path after --graph")),
+                }
+                continue;
+            }
+            "--assign" => {
+                match args.next() {
+                    Some(assignment) => {
+                        let (name, value) = parse_assignment(&assignment)?;
+                        overrides.insert(name, value);
+                    }
+                    None => return Err(anyhow!("Expected name=value after --assign")),
+                }
+                continue;
+            }
+            "-e" => match args.next() {
+                Some(expression) => Source::Inline(expression),
+                None => return Err(anyhow!("Expected an expression after -e")),
+            },
+            "-" => Source::Stdin,
+            flag => match Output::from_flag(flag) {
+                Some(flag_output) => {
+                    output = Some((flag_output, flag.to_string()));
+                    continue;
+                }
+                None => Source::File(arg),
+            },
+        };
+        if source.replace(next_source).is_some() {
+            return Err(anyhow!("Expected just one statement: a file path, -e or -"));
+        }
+    }
+    let source = match source {
+        Some(source) => source,
+        None => {
+            return Err(anyhow!(
+                "No expression provided. Pass a file path, -e \"<expression>\" or - to read stdin"
+            ))
+        }
     };
 
     let env = Env::default().filter_or("LOG_LEVEL", "info");
     env_logger::init_from_env(env);
-    let mut file = File::open(file_path)?;
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer)?;
-    let (ast_root, variables) = parse(&buffer)?;
+    let buffer = source.read()?;
+    // The overrides are seeded into the parser, so the assignments depending on them see them
+    let program = parse(&buffer, strict, overrides.clone())?;
+    for var in unused_variables(&program) {
+        if !overrides.contains_key(&var) {
+            eprintln!("Warning: variable {} is assigned but never used", var);
+        }
+    }
+    let mut statements = program.statements;
+
+    // Options that only apply to the result of a single statement with every variable assigned
+    let result_options: Vec<&str> = [
+        ("--trace", trace),
+        ("--quiet", quiet),
+        ("--exit-code", exit_code),
+        ("--output-dot-and-result", dot_with_result),
+    ]
+    .into_iter()
+    .filter_map(|(flag, given)| given.then_some(flag))
+    .collect();
 
-    let graph_path = Path::new("graph.dot");
-    ast_root.visualize_graph(&graph_path)?;
+    if check_only {
+        let other_options = output.is_some() || graph.is_some() || !result_options.is_empty();
+        if statements.len() > 1 || other_options {
+            return Err(anyhow!(
+                "Subcommand check takes a single statement and no other options"
+            ));
+        }
+        let (root, variables) = statements.remove(0);
+        let (verdict, valid) = check(&root, &variables)?;
+        println!("{}", verdict);
+        if !valid {
+            process::exit(1);
+        }
+        return Ok(());
+    }
 
-    let res = evaluate(ast_root, &variables)?;
-    println!("Result: {}", res);
+    if statements.len() > 1 {
+        if output.is_some() || graph.is_some() || !result_options.is_empty() {
+            return Err(anyhow!(
+                "Only the results are printed for several statements, other options take a single one"
+            ));
+        }
+        for (i, (root, variables)) in statements.iter().enumerate() {
+            if has_unassigned(root, variables) {
+                println!("Result {}:\n{}", i + 1, describe(root, variables)?);
+            } else {
+                println!("Result {}: {}", i + 1, evaluate_ref(root, variables)?);
+            }
+        }
+        return Ok(());
+    }
+    let (ast_root, variables) = statements.remove(0);
+
+    if let Some((output, flag)) = output {
+        if !result_options.is_empty() {
+            return Err(anyhow!(
+                "Can't use {} with {}, it prints no result",
+                result_options.join(", "),
+                flag
+            ));
+        }
+        if let Some(graph) = &graph {
+            write_graph(&ast_root, Path::new(graph), None)?;
+        }
+        return output.print(&ast_root, &variables);
+    }
+
+    if has_unassigned(&ast_root, &variables) {
+        if !result_options.is_empty() {
+            return Err(anyhow!(
+                "Can't use {} without a single result, assign all the variables",
+                result_options.join(", ")
+            ));
+        }
+        if let Some(graph) = &graph {
+            write_graph(&ast_root, Path::new(graph), None)?;
+        }
+        println!("{}", describe(&ast_root, &variables)?);
+        return Ok(());
+    }
+    // The result is written into the graph, so asking for it implies the default graph path
+    if dot_with_result && graph.is_none() {
+        graph = Some(String::from("graph.dot"));
+    }
+    let res = if trace {
+        let (res, steps) = evaluate_trace(&ast_root, &variables)?;
+        if !quiet {
+            for step in steps {
+                println!("{}", step);
+            }
+        }
+        res
+    } else {
+        evaluate_with(&ast_root, &variables)?
+    };
+    if let Some(graph) = &graph {
+        write_graph(&ast_root, Path::new(graph), dot_with_result.then_some(res))?;
+    }
+    if quiet {
+        println!("{}", u8::from(res));
+    } else {
+        println!("Result: {}", res);
+    }
+    if exit_code && !res {
+        process::exit(1);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use logic_solver::parser::StoredVariables;
+
+    use super::{check, describe, has_unassigned, parse, parse_assignment, unused_variables};
+
+    #[test]
+    fn test_unassigned_formula_is_classified() {
+        let (root, variables) = parse("p v ~p", false, StoredVariables::new())
+            .unwrap()
+            .statements
+            .remove(0);
+
+        assert!(has_unassigned(&root, &variables));
+        let output = describe(&root, &variables).unwrap();
+
+        assert_eq!(output, "Simplified: 1\nClassification: tautology");
+    }
+
+    #[test]
+    fn test_assigned_variables_are_substituted() {
+        let (root, variables) = parse("p := 1 p ^ (q v ~~r)", false, StoredVariables::new())
+            .unwrap()
+            .statements
+            .remove(0);
+
+        let output = describe(&root, &variables).unwrap();
+
+        assert_eq!(output, "Simplified: q v r\nClassification: contingent");
+    }
+
+    #[test]
+    fn test_check_verdicts() {
+        let verdict = |contents: &str| {
+            let (root, variables) = parse(contents, false, StoredVariables::new())
+                .unwrap()
+                .statements
+                .remove(0);
+            check(&root, &variables).unwrap()
+        };
+
+        assert_eq!(
+            verdict("p => q => p"),
+            ("Classification: tautology".to_string(), true)
+        );
+        assert_eq!(
+            verdict("p ^ ~p"),
+            ("Classification: contradiction".to_string(), false)
+        );
+        assert_eq!(
+            verdict("p := 0 p v q"),
+            ("Classification: contingent".to_string(), false)
+        );
+        assert_eq!(verdict("1 ^ ~0"), ("Result: true".to_string(), true));
+    }
+
+    #[test]
+    fn test_unused_variables_over_all_statements() {
+        let unused = |contents: &str| {
+            unused_variables(&parse(contents, false, StoredVariables::new()).unwrap())
+        };
+
+        assert_eq!(unused("p := 1 q := 0 r := 1 p ^ 1; r v 0"), vec!["q"]);
+        assert!(unused("p := 1 q := p q").is_empty());
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        assert_eq!(parse_assignment("p=1").unwrap(), ("p".to_string(), true));
+        assert_eq!(
+            parse_assignment("q=false").unwrap(),
+            ("q".to_string(), false)
+        );
+
+        let error = |arg: &str| parse_assignment(arg).unwrap_err().to_string();
+        assert_eq!(error("p"), "Expected name=value after --assign, got 'p'");
+        assert_eq!(error("pq=1"), "Invalid variable name 'pq' in --assign pq=1");
+        assert_eq!(error("=1"), "Invalid variable name '' in --assign =1");
+        assert_eq!(
+            error("p=yes"),
+            "Invalid value 'yes' in --assign p=yes, expected 1, 0, true or false"
+        );
+    }
+}
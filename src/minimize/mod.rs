@@ -0,0 +1,384 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+use crate::interpreter::{assignments, evaluate_ref};
+use crate::lexer::{Operator, Token, Value};
+use crate::parser::{combine, negate_formula, ASTNode, StoredVariables};
+
+/// Largest number of variables `minimize_sop` handles, the truth table and the list
+/// of prime implicants grow too quickly above it
+pub const MAX_MINIMIZE_VARIABLES: usize = 6;
+
+/// Product term over an ordered list of variables.
+/// `Some(value)` is a literal of the variable, `None` a variable eliminated by grouping.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Implicant {
+    pub values: Vec<Option<bool>>,
+}
+
+impl Implicant {
+    fn from_minterm(minterm: u32, count: usize) -> Implicant {
+        Implicant {
+            values: (0..count)
+                .map(|i| Some(minterm_bit(minterm, count, i)))
+                .collect(),
+        }
+    }
+
+    /// Checks whether the minterm (row index with the first variable as the most
+    /// significant bit) is one of the rows grouped by this implicant
+    pub fn covers(&self, minterm: u32) -> bool {
+        let count = self.values.len();
+        self.values
+            .iter()
+            .enumerate()
+            .all(|(i, value)| match value {
+                Some(value) => minterm_bit(minterm, count, i) == *value,
+                None => true,
+            })
+    }
+
+    /// Conjunction of the literals of the product, `1` when no literal is left
+    pub fn to_ast(&self, vars: &[String]) -> ASTNode {
+        let literals = vars.iter().zip(&self.values).filter_map(|(var, value)| {
+            let variable = ASTNode::new(Token::Value(Value::Variable(var.clone())));
+            value.map(|value| {
+                if value {
+                    variable
+                } else {
+                    negate_formula(variable)
+                }
+            })
+        });
+        join(literals, Operator::And)
+            .unwrap_or_else(|| ASTNode::new(Token::Value(Value::Bool(true))))
+    }
+
+    /// Groups two implicants differing in exactly one literal
+    fn merge(&self, other: &Implicant) -> Option<Implicant> {
+        let mut difference = None;
+        for (i, (a, b)) in self.values.iter().zip(&other.values).enumerate() {
+            match (a, b) {
+                (Some(a), Some(b)) if a != b && difference.is_none() => difference = Some(i),
+                (a, b) if a == b => continue,
+                _ => return None,
+            }
+        }
+        difference.map(|i| {
+            let mut values = self.values.clone();
+            values[i] = None;
+            Implicant { values }
+        })
+    }
+}
+
+/// Pattern notation known from Karnaugh maps, e.g. `1-0`
+impl fmt::Display for Implicant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for value in &self.values {
+            let symbol = match value {
+                Some(true) => '1',
+                Some(false) => '0',
+                None => '-',
+            };
+            write!(f, "{}", symbol)?;
+        }
+        Ok(())
+    }
+}
+
+fn minterm_bit(minterm: u32, count: usize, i: usize) -> bool {
+    (minterm >> (count - 1 - i)) & 1 == 1
+}
+
+fn minterm_index(values: &[bool]) -> u32 {
+    values
+        .iter()
+        .fold(0, |index, value| (index << 1) | *value as u32)
+}
+
+/// Indices of the rows of the truth table for which the formula is true
+pub fn minterms(root: &ASTNode, vars: &[String]) -> Result<Vec<u32>> {
+    if vars.len() > u32::BITS as usize {
+        return Err(anyhow!(
+            "Too many variables for minterm indices: {}",
+            vars.len()
+        ));
+    }
+    let mut result = vec![];
+    for values in assignments(vars)? {
+        let index = minterm_index(&values);
+        let assigned: StoredVariables = vars.iter().cloned().zip(values).collect();
+        if evaluate_ref(root, &assigned)? {
+            result.push(index);
+        }
+    }
+    Ok(result)
+}
+
+/// First stage of Quine–McCluskey: implicants are grouped pairwise until no more
+/// merging is possible, the ones that never merged are prime.
+pub fn prime_implicants(root: &ASTNode, vars: &[String]) -> Result<Vec<Implicant>> {
+    let mut current: BTreeSet<Implicant> = minterms(root, vars)?
+        .into_iter()
+        .map(|minterm| Implicant::from_minterm(minterm, vars.len()))
+        .collect();
+    let mut primes = BTreeSet::new();
+
+    while !current.is_empty() {
+        let mut merged = BTreeSet::new();
+        let mut used = BTreeSet::new();
+        for a in &current {
+            for b in &current {
+                if let Some(implicant) = a.merge(b) {
+                    used.insert(a.clone());
+                    used.insert(b.clone());
+                    merged.insert(implicant);
+                }
+            }
+        }
+        primes.extend(current.difference(&used).cloned());
+        current = merged;
+    }
+    Ok(primes.into_iter().collect())
+}
+
+/// Second stage of Quine–McCluskey: picks prime implicants covering all the minterms.
+/// The essential primes, the only ones covering some minterm, are taken first,
+/// the minterms left are covered with the fewest primes found by Petrick's method,
+/// preferring fewer literals between covers of the same size.
+/// Minterms not covered by any of the primes are ignored.
+pub fn select_cover(primes: &[Implicant], minterms: &[u32]) -> Vec<Implicant> {
+    let covering = |minterm: u32| -> Vec<usize> {
+        (0..primes.len())
+            .filter(|i| primes[*i].covers(minterm))
+            .collect()
+    };
+
+    let mut selected = BTreeSet::new();
+    for minterm in minterms {
+        if let [essential] = covering(*minterm)[..] {
+            selected.insert(essential);
+        }
+    }
+
+    // Product of sums over the prime indices, expanded into a sum of products
+    // with supersets of other products dropped along the way
+    let mut products: BTreeSet<BTreeSet<usize>> = BTreeSet::from([BTreeSet::new()]);
+    for minterm in minterms {
+        let candidates = covering(*minterm);
+        if candidates.is_empty() || candidates.iter().any(|i| selected.contains(i)) {
+            continue;
+        }
+        let expanded: BTreeSet<BTreeSet<usize>> = products
+            .iter()
+            .flat_map(|product| {
+                candidates.iter().map(move |i| {
+                    let mut product = product.clone();
+                    product.insert(*i);
+                    product
+                })
+            })
+            .collect();
+        products = expanded
+            .iter()
+            .filter(|product| {
+                !expanded
+                    .iter()
+                    .any(|other| other != *product && other.is_subset(product))
+            })
+            .cloned()
+            .collect();
+    }
+
+    let literals = |product: &BTreeSet<usize>| -> usize {
+        product
+            .iter()
+            .map(|i| primes[*i].values.iter().filter(|v| v.is_some()).count())
+            .sum()
+    };
+    if let Some(best) = products
+        .into_iter()
+        .min_by_key(|product| (product.len(), literals(product)))
+    {
+        selected.extend(best);
+    }
+    selected.into_iter().map(|i| primes[i].clone()).collect()
+}
+
+/// Prime implicants grouping the given satisfying assignment, i.e. the ways
+/// its cell can be circled on a Karnaugh map
+pub fn implicants_covering(
+    root: &ASTNode,
+    vars: &[String],
+    assignment: &StoredVariables,
+) -> Result<Vec<Implicant>> {
+    let mut values = vec![];
+    for var in vars {
+        match assignment.get(var) {
+            Some(value) => values.push(*value),
+            None => return Err(anyhow!("Missing value of variable {}", var)),
+        }
+    }
+    let minterm = minterm_index(&values);
+    Ok(prime_implicants(root, vars)?
+        .into_iter()
+        .filter(|implicant| implicant.covers(minterm))
+        .collect())
+}
+
+/// Chains the nodes with the binary operator, `None` when there are no nodes
+pub(crate) fn join(nodes: impl Iterator<Item = ASTNode>, op: Operator) -> Option<ASTNode> {
+    nodes.reduce(|joined, node| {
+        combine(joined, op.clone(), node).expect("operator used for joining is binary")
+    })
+}
+
+/// Minimal sum of products equivalent to the formula, found with Quine–McCluskey
+/// over its variables in alphabetical order
+pub fn minimize_sop(node: &ASTNode) -> Result<ASTNode> {
+    let vars: Vec<String> = node.free_variables().into_iter().collect();
+    if vars.len() > MAX_MINIMIZE_VARIABLES {
+        return Err(anyhow!(
+            "Too many variables to minimize: {}, the limit is {}",
+            vars.len(),
+            MAX_MINIMIZE_VARIABLES
+        ));
+    }
+    let primes = prime_implicants(node, &vars)?;
+    let cover = select_cover(&primes, &minterms(node, &vars)?);
+    let products = cover.iter().map(|implicant| implicant.to_ast(&vars));
+    Ok(join(products, Operator::Or)
+        .unwrap_or_else(|| ASTNode::new(Token::Value(Value::Bool(false)))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::parser::parse;
+
+    use super::{
+        implicants_covering, minimize_sop, minterms, prime_implicants, select_cover, Implicant,
+    };
+
+    fn vars(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn patterns(implicants: &[Implicant]) -> Vec<String> {
+        implicants.iter().map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn test_minterms() {
+        let root = parse("(p ^ q) v (~p ^ r)");
+
+        let result = minterms(&root, &vars(&["p", "q", "r"])).unwrap();
+
+        assert_eq!(result, vec![1, 3, 6, 7]);
+    }
+
+    #[test]
+    fn test_prime_implicants_include_consensus() {
+        let root = parse("(p ^ q) v (~p ^ r)");
+
+        let result = prime_implicants(&root, &vars(&["p", "q", "r"])).unwrap();
+
+        assert_eq!(patterns(&result), vec!["-11", "0-1", "11-"]);
+    }
+
+    fn implicant(pattern: &str) -> Implicant {
+        Implicant {
+            values: pattern
+                .chars()
+                .map(|c| match c {
+                    '1' => Some(true),
+                    '0' => Some(false),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_select_cover_essential_then_choice() {
+        // 001 is covered only by 0--, which makes it essential. Of the two primes
+        // covering 110, only 1-0 also covers the remaining 100.
+        let primes = vec![implicant("0--"), implicant("-10"), implicant("1-0")];
+
+        let result = select_cover(&primes, &[1, 4, 6]);
+
+        assert_eq!(patterns(&result), vec!["0--", "1-0"]);
+    }
+
+    #[test]
+    fn test_select_cover_drops_consensus() {
+        let root = parse("(p ^ q) v (~p ^ r)");
+        let vars = vars(&["p", "q", "r"]);
+        let primes = prime_implicants(&root, &vars).unwrap();
+
+        let result = select_cover(&primes, &minterms(&root, &vars).unwrap());
+
+        assert_eq!(patterns(&result), vec!["0-1", "11-"]);
+    }
+
+    #[test]
+    fn test_select_cover_cyclic_chart() {
+        // No essential primes here, every minimal cover takes three of the six primes
+        let root = parse("(~p ^ ~q) v (p ^ q) v (q ^ ~r) v (~q ^ r)");
+        let vars = vars(&["p", "q", "r"]);
+        let primes = prime_implicants(&root, &vars).unwrap();
+        let minterms = minterms(&root, &vars).unwrap();
+
+        let result = select_cover(&primes, &minterms);
+
+        assert_eq!(primes.len(), 6);
+        assert_eq!(result.len(), 3);
+        assert!(minterms
+            .iter()
+            .all(|m| result.iter().any(|implicant| implicant.covers(*m))));
+    }
+
+    #[test]
+    fn test_implicants_covering_minterm() {
+        let root = parse("(p ^ q) v (~p ^ r)");
+        let assignment = HashMap::from([
+            ("p".to_string(), false),
+            ("q".to_string(), true),
+            ("r".to_string(), true),
+        ]);
+
+        let result = implicants_covering(&root, &vars(&["p", "q", "r"]), &assignment).unwrap();
+
+        assert_eq!(patterns(&result), vec!["-11", "0-1"]);
+    }
+
+    #[test]
+    fn test_implicants_covering_should_fail_on_missing_variable() {
+        let root = parse("p v q");
+        let assignment = HashMap::from([("p".to_string(), true)]);
+
+        let result = implicants_covering(&root, &vars(&["p", "q"]), &assignment);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_minimize_sop() {
+        let minimized = |expr: &str| minimize_sop(&parse(expr)).unwrap().to_expr_string();
+
+        assert_eq!(minimized("~(~p v ~q)"), "p ^ q");
+        assert_eq!(minimized("p v ~p"), "1");
+        assert_eq!(minimized("p ^ ~p"), "0");
+        assert_eq!(minimized("p ^ q v p ^ ~q"), "p");
+        assert_eq!(minimized("(p ^ q) v (~p ^ r) v (q ^ r)"), "~p ^ r v p ^ q");
+    }
+
+    #[test]
+    fn test_minimize_sop_should_fail_above_limit() {
+        assert!(minimize_sop(&parse("a ^ b ^ c ^ d ^ e ^ f ^ g")).is_err());
+    }
+}
@@ -1,133 +1,627 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter::Peekable;
 
 use anyhow::{anyhow, Result};
 use log::debug;
 
-use crate::lexer::{Lexer, Operator, Token, Value};
+use crate::interpreter::{evaluate_ref, undefined_variables};
+use crate::lexer::{Lexer, Operator, Span, Spanned, Token, Value};
 use crate::parser::{ASTNode, StoredVariables};
 
-fn make_node(tree_queue: &mut Vec<ASTNode>, operator: Operator) {
-    if let Some(right) = tree_queue.pop() {
-        // Special case for unary operators
-        let node = if let Operator::Not = operator {
-            ASTNode {
-                token: Token::Operator(operator),
-                left: Some(Box::new(right)),
-                right: None,
-            }
-        } else {
-            match tree_queue.pop() {
-                Some(left) => ASTNode {
-                    token: Token::Operator(operator),
-                    left: Some(Box::new(left)),
-                    right: Some(Box::new(right)),
-                },
-                None => ASTNode {
+/// Trees waiting for their operator, each with its depth, so the depth of a new node
+/// is known without walking its operands again
+struct TreeQueue {
+    trees: Vec<ASTNode>,
+    depths: Vec<usize>,
+    max_depth: usize,
+}
+
+impl TreeQueue {
+    fn new(max_depth: usize) -> TreeQueue {
+        TreeQueue {
+            trees: vec![],
+            depths: vec![],
+            max_depth,
+        }
+    }
+
+    fn push(&mut self, node: ASTNode, depth: usize) -> Result<()> {
+        if depth > self.max_depth {
+            return Err(anyhow!("Expression too deeply nested"));
+        }
+        self.trees.push(node);
+        self.depths.push(depth);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<(ASTNode, usize)> {
+        Some((self.trees.pop()?, self.depths.pop()?))
+    }
+
+    fn make_node(&mut self, operator: Operator) -> Result<()> {
+        if let Some((right, right_depth)) = self.pop() {
+            // Special case for unary operators
+            let (node, depth) = if let Operator::Not = operator {
+                let node = ASTNode {
                     token: Token::Operator(operator),
                     left: Some(Box::new(right)),
                     right: None,
-                },
-            }
-        };
-        tree_queue.push(node);
+                };
+                (node, right_depth)
+            } else {
+                match self.pop() {
+                    Some((left, left_depth)) => {
+                        let node = ASTNode {
+                            token: Token::Operator(operator),
+                            left: Some(Box::new(left)),
+                            right: Some(Box::new(right)),
+                        };
+                        (node, left_depth.max(right_depth))
+                    }
+                    None => {
+                        let node = ASTNode {
+                            token: Token::Operator(operator),
+                            left: Some(Box::new(right)),
+                            right: None,
+                        };
+                        (node, right_depth)
+                    }
+                }
+            };
+            self.push(node, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deepest tree, and nesting of parentheses, the parser and the evaluator accept by default
+pub const MAX_DEPTH: usize = 1000;
+
+/// Switches changing what the parser accepts
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseOptions {
+    /// Keep variables that were never assigned with `:=` as leaves of the tree
+    /// instead of rejecting them, so the formula can be analysed over them later
+    pub free_variables: bool,
+    /// Treat operands written next to each other, like `pq` or `p(q v r)`,
+    /// as joined with `^` instead of rejecting them
+    pub implicit_and: bool,
+    /// How tightly the binary operators bind
+    pub precedence: Precedence,
+    /// Deepest tree and nesting of parentheses accepted, `MAX_DEPTH` unless changed.
+    /// Every operand counts, so `~~p` is as deep as `((p ^ q) ^ r)`.
+    pub max_depth: usize,
+    /// Reject `=>` and `<=>` mixed at the same level of parentheses, like `p => q <=> r`,
+    /// instead of grouping them by their precedence
+    pub strict: bool,
+    /// Values fixed by the caller. They are known from the start, so the expressions
+    /// assigned with `:=` see them, and they win over the values assigned to the same variables.
+    pub overrides: StoredVariables,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            free_variables: false,
+            implicit_and: false,
+            precedence: Precedence::default(),
+            max_depth: MAX_DEPTH,
+            strict: false,
+            overrides: StoredVariables::new(),
+        }
+    }
+}
+
+/// Binding strength of the binary operators, the higher the tighter.
+/// The default follows the order of the `Operator` variants,
+/// from equivalence binding the loosest to nand binding the tightest.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Precedence {
+    levels: BTreeMap<Operator, u8>,
+}
+
+impl Default for Precedence {
+    fn default() -> Self {
+        let operators = [
+            Operator::Equivalence,
+            Operator::Implication,
+            Operator::Or,
+            Operator::Nor,
+            Operator::Xor,
+            Operator::And,
+            Operator::Nand,
+        ];
+        Precedence {
+            levels: operators.into_iter().zip(1..).collect(),
+        }
+    }
+}
+
+impl Precedence {
+    /// Same table with the level of the operator changed
+    pub fn with(mut self, operator: Operator, level: u8) -> Precedence {
+        self.levels.insert(operator, level);
+        self
+    }
+
+    /// Level of the operator. The ones without a level, like `~`, bind tighter than any binary one.
+    pub fn level(&self, operator: &Operator) -> u8 {
+        self.levels.get(operator).copied().unwrap_or(u8::MAX)
+    }
+}
+
+/// Pops the operators binding at least as tight as the incoming one into the tree
+fn push_operator(
+    operators: &mut Vec<Operator>,
+    tree_queue: &mut TreeQueue,
+    current_op: Operator,
+    precedence: &Precedence,
+) -> Result<()> {
+    // Prefix operator has no left operand, so nothing before it can be reduced yet
+    if current_op == Operator::Not {
+        operators.push(current_op);
+        return Ok(());
     }
+    while let Some(op) = operators.pop() {
+        // Left parenthesis is treated separately, because it has
+        // precedence property (highest) in this implementation.
+        let (level, current_level) = (precedence.level(&op), precedence.level(&current_op));
+        let groups_right = level == current_level && current_op.is_right_associative();
+        if op == Operator::ParenthisOpen || level < current_level || groups_right {
+            operators.push(op);
+            break;
+        }
+        tree_queue.make_node(op)?;
+    }
+    operators.push(current_op);
+    Ok(())
 }
 
-/// Shunting yard algorithm with simple state machine for assigning variables
+/// Shunting yard algorithm with simple state machine for assigning variables.
+/// A statement is a sequence of assignments `variable := expression` followed by
+/// the expression to evaluate. The assigned expression is evaluated right away
+/// and only its value is stored, so `p := 1 ^ 0 p v 0` is `0 v 0` in the end.
 pub fn construct_ast(lexer: &mut Lexer) -> Result<(ASTNode, StoredVariables)> {
-    let mut stored_variables: StoredVariables = HashMap::new();
+    construct_ast_with(lexer, &ParseOptions::default())
+}
+
+pub fn construct_ast_with(
+    lexer: &mut Lexer,
+    options: &ParseOptions,
+) -> Result<(ASTNode, StoredVariables)> {
+    construct(lexer, options, &mut |_, _, _| {})
+}
+
+/// Parses statements separated with `;`, each into its own AST.
+/// Variables assigned in a statement stay assigned in the following ones,
+/// and statements made of assignments alone only contribute their variables.
+pub fn parse_program(contents: &str) -> Result<Vec<(ASTNode, StoredVariables)>> {
+    parse_program_with(contents, &ParseOptions::default())
+}
+
+pub fn parse_program_with(
+    contents: &str,
+    options: &ParseOptions,
+) -> Result<Vec<(ASTNode, StoredVariables)>> {
+    Ok(construct_program(contents, options)?.statements)
+}
+
+/// Statements of a program, as `parse_program_with` returns them,
+/// along with the variables read by the expressions assigned with `:=`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    /// Tree of every statement with an expression, and the variables assigned up to it
+    pub statements: Vec<(ASTNode, StoredVariables)>,
+    /// Variables read on the right of `:=`, e.g. `p` in `q := ~p`
+    pub assignment_reads: BTreeSet<String>,
+}
+
+/// Same as `parse_program_with`, also recording the variables the assignments read
+pub fn construct_program(contents: &str, options: &ParseOptions) -> Result<Program> {
+    let tokens = Lexer::new(contents).collect::<Result<Vec<Spanned<Token>>>>()?;
+
+    let mut variables = options.overrides.clone();
+    let mut program = Program {
+        statements: vec![],
+        assignment_reads: BTreeSet::new(),
+    };
+    for statement in tokens.split(|token| token.value == Token::Operator(Operator::Separator)) {
+        if statement.is_empty() {
+            continue;
+        }
+        let tokens = statement.iter().cloned().map(Ok);
+        let parsed = construct_statement(tokens, options, variables, &mut |_, _, _| {})?;
+        variables = parsed.variables;
+        program.assignment_reads.extend(parsed.assignment_reads);
+        if let Some(root) = parsed.root {
+            program.statements.push((root, variables.clone()));
+        }
+    }
+    if program.statements.is_empty() {
+        return Err(no_expression(&variables));
+    }
+    Ok(program)
+}
+
+/// Variables used before being assigned with `:=`, each with the position of its first use,
+/// and the variables assigned by then
+#[derive(Default)]
+struct Undefined {
+    uses: Vec<(String, Span)>,
+    defined: StoredVariables,
+}
+
+fn is_assign(token: Option<&Spanned<Token>>) -> bool {
+    matches!(
+        token,
+        Some(Spanned {
+            value: Token::Operator(Operator::Assign),
+            ..
+        })
+    )
+}
+
+/// Takes the tokens of the expression right of `:=`. It ends before the first token
+/// that can't continue it, like an operand written right after another one.
+fn take_expression(
+    tokens: &mut Peekable<impl Iterator<Item = Result<Spanned<Token>>>>,
+) -> Result<Vec<Spanned<Token>>> {
+    let mut expression = vec![];
+    let mut after_operand = false;
+    let mut depth = 0;
+    while let Some(next) = tokens.peek() {
+        let token = match next {
+            Ok(next) => &next.value,
+            Err(_) => break,
+        };
+        let continues = match token {
+            Token::Value(_) => !after_operand,
+            Token::Operator(Operator::ParenthisOpen | Operator::Not) => !after_operand,
+            Token::Operator(Operator::ParenthisClosed) => depth > 0 && after_operand,
+            Token::Operator(op) => op.is_binary(),
+        };
+        if !continues {
+            break;
+        }
+        match token {
+            Token::Operator(Operator::ParenthisOpen) => depth += 1,
+            Token::Operator(Operator::ParenthisClosed) => depth -= 1,
+            _ => {}
+        }
+        after_operand = matches!(
+            token,
+            Token::Value(_) | Token::Operator(Operator::ParenthisClosed)
+        );
+        if let Some(token) = tokens.next() {
+            expression.push(token?);
+        }
+    }
+    Ok(expression)
+}
+
+/// Evaluates the expression assigned to the variable over the variables assigned so far,
+/// along with the variables it reads. Variables assigned only later can't be used,
+/// which also rules out circular assignments.
+fn assigned_value(
+    variable: &str,
+    expression: Vec<Spanned<Token>>,
+    options: &ParseOptions,
+    stored_variables: &StoredVariables,
+    observer: &mut StepObserver,
+) -> Result<(bool, BTreeSet<String>)> {
+    let options = ParseOptions {
+        free_variables: true,
+        ..options.clone()
+    };
+    let tokens = expression.into_iter().map(Ok);
+    let root = match construct_statement(tokens, &options, stored_variables.clone(), observer)?.root
+    {
+        Some(root) => root,
+        None => return Err(anyhow!("Missing value assigned to {}", variable)),
+    };
+    let read = root.free_variables();
+    if let Some(unassigned) = read
+        .iter()
+        .find(|name| !stored_variables.contains_key(*name))
+    {
+        return Err(anyhow!(
+            "Variable {} is used in the assignment of {} before being assigned",
+            unassigned,
+            variable
+        ));
+    }
+    Ok((evaluate_ref(&root, stored_variables)?, read))
+}
+
+/// Describes the operator on top of the stack when no operand followed it
+fn missing_operand(op: &Operator) -> String {
+    match op {
+        Operator::ParenthisOpen => "Empty parentheses".to_string(),
+        Operator::Not => "Operator 'Not' missing operand".to_string(),
+        op => format!("Operator '{:?}' missing right operand", op),
+    }
+}
+
+type StepObserver<'a> = dyn FnMut(&Token, &[Operator], &[ASTNode]) + 'a;
+
+/// Parser over any stream of tokens. The observer is called after each token
+/// is handled, with the operator stack and tree queue it left behind.
+pub(super) fn construct(
+    tokens: impl Iterator<Item = Result<Spanned<Token>>>,
+    options: &ParseOptions,
+    observer: &mut StepObserver,
+) -> Result<(ASTNode, StoredVariables)> {
+    let parsed = construct_statement(tokens, options, options.overrides.clone(), observer)?;
+    match parsed.root {
+        Some(root) => Ok((root, parsed.variables)),
+        None => Err(no_expression(&parsed.variables)),
+    }
+}
+
+/// Error for input without a tree, telling apart empty input from assignments alone
+fn no_expression(stored_variables: &StoredVariables) -> anyhow::Error {
+    if stored_variables.is_empty() {
+        anyhow!("Invalid syntax: empty expression")
+    } else {
+        anyhow!("No expression to evaluate after variable assignments")
+    }
+}
+
+/// Statement as parsed on top of the variables assigned before it
+struct Statement {
+    /// Missing when the statement only assigns variables
+    root: Option<ASTNode>,
+    /// Variables assigned so far, including the ones of the statement
+    variables: StoredVariables,
+    /// Variables read by the expressions the statement assigns
+    assignment_reads: BTreeSet<String>,
+}
+
+/// Parses a single statement on top of the variables assigned so far.
+/// Unless they are free, the variables used before being assigned are all listed,
+/// as the parsing goes on past them, and they are reported before any later error.
+fn construct_statement(
+    tokens: impl Iterator<Item = Result<Spanned<Token>>>,
+    options: &ParseOptions,
+    stored_variables: StoredVariables,
+    observer: &mut StepObserver,
+) -> Result<Statement> {
+    let mut undefined = Undefined::default();
+    let parsed = parse_statement(tokens, options, stored_variables, observer, &mut undefined);
+    if undefined.uses.is_empty() {
+        parsed
+    } else {
+        let uses: Vec<(&str, Option<&Span>)> = undefined
+            .uses
+            .iter()
+            .map(|(name, span)| (name.as_str(), Some(span)))
+            .collect();
+        Err(undefined_variables(&uses, &undefined.defined))
+    }
+}
+
+fn parse_statement(
+    tokens: impl Iterator<Item = Result<Spanned<Token>>>,
+    options: &ParseOptions,
+    mut stored_variables: StoredVariables,
+    observer: &mut StepObserver,
+    undefined: &mut Undefined,
+) -> Result<Statement> {
+    let mut assignment_reads = BTreeSet::new();
     let mut operators: Vec<Operator> = Vec::new();
-    let mut tree_queue: Vec<ASTNode> = Vec::new();
+    let mut tree_queue = TreeQueue::new(options.max_depth);
     let mut variable_name: Option<String> = None;
-    let mut assignment = false;
+    let mut after_operand = false;
+    // Positions of the parentheses still waiting for their closing one
+    let mut open_parentheses: Vec<Span> = vec![];
+    // Whether `=>` or `<=>` was seen inside each of them, for the strict mode
+    let mut arrows: Vec<Option<Operator>> = vec![None];
+    let mut tokens = tokens.peekable();
 
-    while let Some(token) = lexer.next() {
+    while let Some(token) = tokens.next() {
         debug!("{:?}", token);
         debug!("{:#?}", operators);
+        let Spanned { value: token, span } = token?;
+        let handled = token.clone();
+        let assign_follows = is_assign(tokens.peek().and_then(|next| next.as_ref().ok()));
 
-        match token {
-            Err(e) => return Err(e),
-            Ok(Token::Value(value)) => match value {
-                Value::Bool(boolean_v) => {
-                    if assignment {
-                        if let Some(var) = variable_name.take() {
-                            stored_variables.insert(var, boolean_v);
-                            assignment = false;
+        let starts_operand = match &token {
+            Token::Value(Value::Bool(_)) => true,
+            Token::Value(Value::Variable(_)) => !assign_follows,
+            Token::Operator(Operator::ParenthisOpen) | Token::Operator(Operator::Not) => true,
+            _ => false,
+        };
+        if starts_operand && after_operand {
+            if options.implicit_and {
+                push_operator(
+                    &mut operators,
+                    &mut tree_queue,
+                    Operator::And,
+                    &options.precedence,
+                )
+                .map_err(|err| anyhow!("{} at {}", err, span))?;
+            } else {
+                return Err(anyhow!(
+                    "Invalid syntax: expected an operator before {} at {}",
+                    token,
+                    span
+                ));
+            }
+        }
+        let needs_left = matches!(&token, Token::Operator(op) if op.is_binary());
+        if needs_left && !after_operand {
+            return Err(anyhow!(
+                "Operator '{}' missing left operand at {}",
+                token,
+                span
+            ));
+        }
+        if token == Token::Operator(Operator::ParenthisClosed) && !after_operand {
+            if let Some(op) = operators.last() {
+                return Err(anyhow!("{} at {}", missing_operand(op), span));
+            }
+        }
+        after_operand = match &token {
+            Token::Value(_) => starts_operand,
+            Token::Operator(Operator::ParenthisClosed) => true,
+            _ => false,
+        };
+        if options.strict {
+            match &token {
+                Token::Operator(Operator::ParenthisOpen) => arrows.push(None),
+                Token::Operator(Operator::ParenthisClosed) if arrows.len() > 1 => {
+                    arrows.pop();
+                }
+                Token::Operator(op @ (Operator::Implication | Operator::Equivalence)) => {
+                    if let Some(seen) = arrows.last_mut() {
+                        match seen {
+                            Some(other) if other != op => {
+                                return Err(anyhow!(
+                                    "Operators '=>' and '<=>' mixed without parentheses at {}",
+                                    span
+                                ))
+                            }
+                            _ => *seen = Some(op.clone()),
                         }
-                    } else {
-                        tree_queue.push(ASTNode::new(Token::Value(Value::Bool(boolean_v))));
                     }
                 }
+                _ => {}
+            }
+        }
+
+        match token {
+            Token::Value(value) => match value {
+                Value::Bool(boolean_v) => {
+                    tree_queue.push(ASTNode::new(Token::Value(Value::Bool(boolean_v))), 1)?;
+                }
                 Value::Variable(variable) => {
-                    match stored_variables.get(&variable) {
-                        Some(_) => {
-                            tree_queue.push(ASTNode::new(Token::Value(Value::Variable(variable))))
+                    if assign_follows {
+                        variable_name = Some(variable);
+                    } else {
+                        let defined =
+                            options.free_variables || stored_variables.contains_key(&variable);
+                        if !defined && undefined.uses.iter().all(|(name, _)| *name != variable) {
+                            // Assignments only come first, so the later uses see the same ones
+                            if undefined.uses.is_empty() {
+                                undefined.defined = stored_variables.clone();
+                            }
+                            undefined.uses.push((variable.clone(), span));
                         }
-                        None => variable_name = Some(variable),
-                    };
+                        tree_queue
+                            .push(ASTNode::new(Token::Value(Value::Variable(variable))), 1)?;
+                    }
                 }
             },
-            Ok(Token::Operator(operator)) => match operator {
-                Operator::Assign => match variable_name {
-                    Some(_) => assignment = true,
-                    None => return Err(anyhow!("Invalid syntax: found no variable to assign.")),
+            Token::Operator(operator) => match operator {
+                Operator::Assign if !operators.is_empty() || !tree_queue.trees.is_empty() => {
+                    return Err(anyhow!(
+                        "Assignment ':=' at {} is only allowed at statement start",
+                        span
+                    ))
+                }
+                Operator::Assign => match variable_name.take() {
+                    Some(variable) => {
+                        observer(&handled, &operators, &tree_queue.trees);
+                        let expression = take_expression(&mut tokens)?;
+                        if let Some(Ok(next)) = tokens.peek() {
+                            if next.value == Token::Operator(Operator::Assign) {
+                                return Err(anyhow!(
+                                    "Chained assignment at {}, assign every variable on its own",
+                                    next.span
+                                ));
+                            }
+                        }
+                        let (value, read) = assigned_value(
+                            &variable,
+                            expression,
+                            options,
+                            &stored_variables,
+                            observer,
+                        )?;
+                        assignment_reads.extend(read);
+                        let value = options.overrides.get(&variable).copied().unwrap_or(value);
+                        stored_variables.insert(variable, value);
+                        continue;
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "Invalid syntax: found no variable to assign at {}.",
+                            span
+                        ))
+                    }
                 },
-                Operator::ParenthisOpen => operators.push(Operator::ParenthisOpen),
+                Operator::Separator => {
+                    return Err(anyhow!(
+                        "Unexpected ';' at {}, only a program can have several statements",
+                        span
+                    ))
+                }
+                Operator::ParenthisOpen => {
+                    if open_parentheses.len() >= options.max_depth {
+                        return Err(anyhow!("Expression too deeply nested at {}", span));
+                    }
+                    open_parentheses.push(span);
+                    operators.push(Operator::ParenthisOpen)
+                }
                 Operator::ParenthisClosed => {
+                    if open_parentheses.pop().is_none() {
+                        return Err(anyhow!("Unexpected ')' at {}", span));
+                    }
                     while let Some(inner_op) = operators.pop() {
                         match inner_op {
                             Operator::ParenthisOpen => break,
                             Operator::ParenthisClosed => break,
-                            op => make_node(&mut tree_queue, op),
-                        }
-                    }
-                }
-                current_op => {
-                    let mut v = vec![];
-                    while let Some(op) = operators.pop() {
-                        match op {
-                            // Left parenthesis is treated separately, because it has
-                            // precedence property (highest) in this implementation.
-                            Operator::ParenthisOpen => {
-                                v.push(op);
-                                break;
-                            }
-                            _ => {
-                                if op >= current_op {
-                                    make_node(&mut tree_queue, op);
-                                } else {
-                                    v.push(op);
-                                }
-                            }
+                            op => tree_queue
+                                .make_node(op)
+                                .map_err(|err| anyhow!("{} at {}", err, span))?,
                         }
                     }
-                    operators.extend(v);
-                    operators.push(current_op);
                 }
+                current_op => push_operator(
+                    &mut operators,
+                    &mut tree_queue,
+                    current_op,
+                    &options.precedence,
+                )
+                .map_err(|err| anyhow!("{} at {}", err, span))?,
             },
         }
+        observer(&handled, &operators, &tree_queue.trees);
+    }
+    if !after_operand {
+        if let Some(op) = operators.last() {
+            return Err(anyhow!("{}", missing_operand(op)));
+        }
+    }
+    if let Some(span) = open_parentheses.first() {
+        return Err(anyhow!("Unmatched '(' at {}", span));
     }
     for op in operators.into_iter().rev() {
-        make_node(&mut tree_queue, op);
+        tree_queue.make_node(op)?;
     }
 
     if let Some(variable) = variable_name {
         return Err(anyhow!("Undefined variable {}", variable));
     };
 
-    match tree_queue.pop() {
-        Some(root) => Ok((root, stored_variables)),
-        None => Err(anyhow!(
-            "Invalid syntax, expected at least one AST node left"
-        )),
-    }
+    Ok(Statement {
+        root: tree_queue.pop().map(|(root, _)| root),
+        variables: stored_variables,
+        assignment_reads,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
     use std::collections::HashMap;
 
-    use super::construct_ast;
+    use super::{
+        construct_ast, construct_ast_with, construct_program, parse_program, parse_program_with,
+        ParseOptions, Precedence,
+    };
     use crate::{
         lexer::{Lexer, Operator, Token, Value},
         parser::ASTNode,
@@ -284,6 +778,25 @@ mod tests {
         assert_eq!(results, expected);
     }
 
+    #[test]
+    fn test_construct_ast_negation_nested() {
+        let mut lexer = Lexer::new("~~1 ^ 0");
+        let (results, _) = construct_ast(&mut lexer).unwrap();
+
+        let mut not = ASTNode::new(Token::Operator(Operator::Not));
+        not.add_left_token(Token::Value(Value::Bool(true)));
+        let mut not2 = ASTNode::new(Token::Operator(Operator::Not));
+        not2.add_left_child(not);
+
+        let expected = ASTNode {
+            token: Token::Operator(Operator::And),
+            left: Some(Box::new(not2)),
+            right: Some(Box::new(ASTNode::new(Token::Value(Value::Bool(false))))),
+        };
+
+        assert_eq!(results, expected);
+    }
+
     #[test]
     fn test_construct_ast_longer_statement() {
         let mut lexer = Lexer::new("0 ^ 1 v 0 ^ 1");
@@ -332,6 +845,68 @@ mod tests {
         assert_eq!(results, expected);
     }
 
+    #[test]
+    fn test_construct_ast_lower_precedence_deeper_in_stack() {
+        let mut lexer = Lexer::new("1 <=> 0 v 1 ^ 0");
+        let (results, _) = construct_ast(&mut lexer).unwrap();
+
+        let mut and = ASTNode::new(Token::Operator(Operator::And));
+        and.add_left_token(Token::Value(Value::Bool(true)));
+        and.add_right_token(Token::Value(Value::Bool(false)));
+
+        let mut or = ASTNode::new(Token::Operator(Operator::Or));
+        or.add_left_token(Token::Value(Value::Bool(false)));
+        or.add_right_child(and);
+
+        let expected = ASTNode {
+            token: Token::Operator(Operator::Equivalence),
+            left: Some(Box::new(ASTNode::new(Token::Value(Value::Bool(true))))),
+            right: Some(Box::new(or)),
+        };
+
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_construct_ast_implication_is_right_associative() {
+        let (results, _) = construct_ast(&mut Lexer::new("1 => 1 => 0")).unwrap();
+
+        let mut inner = ASTNode::new(Token::Operator(Operator::Implication));
+        inner.add_left_token(Token::Value(Value::Bool(true)));
+        inner.add_right_token(Token::Value(Value::Bool(false)));
+        let expected = ASTNode {
+            token: Token::Operator(Operator::Implication),
+            left: Some(Box::new(ASTNode::new(Token::Value(Value::Bool(true))))),
+            right: Some(Box::new(inner)),
+        };
+        assert_eq!(results, expected);
+
+        let (results, _) = construct_ast(&mut Lexer::new("1 v 0 => 0 ^ 1 => 0")).unwrap();
+        assert_eq!(results.to_expr_string(), "1 v 0 => 0 ^ 1 => 0");
+    }
+
+    #[test]
+    fn test_construct_ast_custom_precedence() {
+        let free = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let swapped = ParseOptions {
+            precedence: Precedence::default()
+                .with(Operator::Implication, 3)
+                .with(Operator::Or, 2),
+            ..free.clone()
+        };
+
+        let (default, _) = construct_ast_with(&mut Lexer::new("a => b v c"), &free).unwrap();
+        let (custom, _) = construct_ast_with(&mut Lexer::new("a => b v c"), &swapped).unwrap();
+
+        let (expected, _) = construct_ast_with(&mut Lexer::new("a => (b v c)"), &free).unwrap();
+        assert_eq!(default, expected);
+        let (expected, _) = construct_ast_with(&mut Lexer::new("(a => b) v c"), &free).unwrap();
+        assert_eq!(custom, expected);
+    }
+
     #[test]
     fn test_construct_ast_with_variables() {
         let mut lexer = Lexer::new("p := 0 q := 1 ~p v ~q");
@@ -363,6 +938,423 @@ mod tests {
 
         assert!(results.is_err());
     }
+
+    #[test]
+    fn test_construct_ast_lists_all_undefined_variables() {
+        let mut lexer = Lexer::new("p := 0 (q ^ p) v ~w v q");
+        let error = construct_ast(&mut lexer).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Undefined variables 'q' at 1:9, 'w' at 1:19; defined: [p]"
+        );
+    }
+
+    #[test]
+    fn test_construct_ast_variable_used_before_assignment() {
+        let mut lexer = Lexer::new("p ^ 1 p := 0");
+        let error = construct_ast(&mut lexer).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Undefined variable 'p' at 1:1; defined: []"
+        );
+    }
+
+    #[test]
+    fn test_construct_ast_with_free_variables() {
+        let mut lexer = Lexer::new("p := 0 ~p v w");
+        let options = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let (results, vars) = construct_ast_with(&mut lexer, &options).unwrap();
+
+        let mut not = ASTNode::new(Token::Operator(Operator::Not));
+        not.add_left_token(Token::Value(Value::Variable("p".to_string())));
+
+        let expected = ASTNode {
+            token: Token::Operator(Operator::Or),
+            left: Some(Box::new(not)),
+            right: Some(Box::new(ASTNode::new(Token::Value(Value::Variable(
+                "w".to_string(),
+            ))))),
+        };
+
+        let mut expected_vars = HashMap::new();
+        expected_vars.insert("p".to_string(), false);
+
+        assert_eq!(results, expected);
+        assert_eq!(vars, expected_vars);
+    }
+
+    #[test]
+    fn test_construct_ast_juxtaposition_is_rejected() {
+        let mut lexer = Lexer::new("pq");
+        let options = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let results = construct_ast_with(&mut lexer, &options);
+
+        assert!(results.is_err());
+    }
+
+    #[test]
+    fn test_construct_ast_implicit_and() {
+        let options = ParseOptions {
+            free_variables: true,
+            implicit_and: true,
+            ..Default::default()
+        };
+        let (results, _) = construct_ast_with(&mut Lexer::new("pq"), &options).unwrap();
+
+        let mut and = ASTNode::new(Token::Operator(Operator::And));
+        and.add_left_token(Token::Value(Value::Variable("p".to_string())));
+        and.add_right_token(Token::Value(Value::Variable("q".to_string())));
+
+        assert_eq!(results, and);
+    }
+
+    #[test]
+    fn test_construct_ast_implicit_and_before_parenthesis() {
+        let options = ParseOptions {
+            free_variables: true,
+            implicit_and: true,
+            ..Default::default()
+        };
+        let (results, _) = construct_ast_with(&mut Lexer::new("p(q v r) v ~pr"), &options).unwrap();
+        let (expected, _) =
+            construct_ast_with(&mut Lexer::new("(p ^ (q v r)) v (~p ^ r)"), &options).unwrap();
+
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_construct_ast_unbalanced_parentheses() {
+        let error = |expr: &str| {
+            construct_ast(&mut Lexer::new(expr))
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(error("(1 ^ 0"), "Unmatched '(' at 1:1");
+        assert_eq!(error("1 ^ 0)"), "Unexpected ')' at 1:6");
+        assert_eq!(error(")1("), "Unexpected ')' at 1:1");
+        assert_eq!(error("((1) ^ (0)"), "Unmatched '(' at 1:1");
+    }
+
+    #[test]
+    fn test_construct_ast_missing_operands() {
+        let error = |expr: &str| {
+            construct_ast(&mut Lexer::new(expr))
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(error("1 ^"), "Operator 'And' missing right operand");
+        assert_eq!(error("^ 1"), "Operator 'And' missing left operand at 1:1");
+        assert_eq!(error("~"), "Operator 'Not' missing operand");
+        assert_eq!(error(""), "Invalid syntax: empty expression");
+        assert_eq!(
+            error("1 v (0 =>)"),
+            "Operator 'Implication' missing right operand at 1:10"
+        );
+        assert_eq!(error("1 ^ ()"), "Empty parentheses at 1:6");
+        assert_eq!(error("1 ^ (~)"), "Operator 'Not' missing operand at 1:7");
+    }
+
+    #[test]
+    fn test_construct_ast_assignments_without_expression() {
+        let result = construct_ast(&mut Lexer::new("p := 1 q := 0"));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No expression to evaluate after variable assignments"
+        );
+        let result = parse_program("p := 1; q := p;");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No expression to evaluate after variable assignments"
+        );
+
+        let (root, variables) = construct_ast(&mut Lexer::new("p := 1 q := 0 q")).unwrap();
+        assert_eq!(root.to_expr_string(), "q");
+        assert_eq!(variables.len(), 2);
+    }
+
+    #[test]
+    fn test_construct_ast_assignment_of_variable() {
+        let (root, vars) = construct_ast(&mut Lexer::new("p := 1  q := p  q ^ 1")).unwrap();
+
+        assert_eq!(root.to_expr_string(), "q ^ 1");
+        assert_eq!(vars.get("q"), Some(&true));
+    }
+
+    #[test]
+    fn test_construct_ast_assignment_of_expression() {
+        let mut lexer = Lexer::new("p := 1 q := 0 r := ~(p ^ q) v q s := r => q r ^ s");
+        let (root, vars) = construct_ast(&mut lexer).unwrap();
+
+        assert_eq!(root.to_expr_string(), "r ^ s");
+        assert_eq!(vars.get("r"), Some(&true));
+        assert_eq!(vars.get("s"), Some(&false));
+    }
+
+    #[test]
+    fn test_construct_ast_assignment_is_not_part_of_expression() {
+        let (root, vars) = construct_ast(&mut Lexer::new("p := 1 ^ 0  p v 0")).unwrap();
+
+        assert_eq!(root.to_expr_string(), "p v 0");
+        assert_eq!(vars, HashMap::from([("p".to_string(), false)]));
+
+        let free = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let result = construct_ast_with(&mut Lexer::new("p := q := 1  p"), &free);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Chained assignment at 1:8, assign every variable on its own"
+        );
+    }
+
+    #[rstest]
+    #[case("1 ^ (p := 0)", "1:8")]
+    #[case("p := 1 (q := 0)", "1:11")]
+    #[case("p ^ q := 0", "1:7")]
+    fn test_construct_ast_misplaced_assignment(#[case] expression: &str, #[case] position: &str) {
+        let free = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let result = construct_ast_with(&mut Lexer::new(expression), &free);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "Assignment ':=' at {} is only allowed at statement start",
+                position
+            )
+        );
+    }
+
+    #[test]
+    fn test_construct_ast_depth_limit() {
+        let nested = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+
+        let result = construct_ast(&mut Lexer::new(&nested));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Expression too deeply nested at 1:1001"
+        );
+        let shallow = ParseOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert!(construct_ast_with(&mut Lexer::new("((1)) ^ (0)"), &shallow).is_ok());
+        assert!(construct_ast_with(&mut Lexer::new("(((1)))"), &shallow).is_err());
+    }
+
+    #[rstest]
+    #[case("~~p", "Expression too deeply nested")]
+    #[case("p ^ q ^ r", "Expression too deeply nested")]
+    #[case("p ^ q ^ r ^ s", "Expression too deeply nested at 1:11")]
+    #[case("p => q => r", "Expression too deeply nested")]
+    #[case("(~~p) v q", "Expression too deeply nested at 1:5")]
+    fn test_construct_ast_counts_depth_of_operands(#[case] expression: &str, #[case] error: &str) {
+        let options = ParseOptions {
+            max_depth: 2,
+            free_variables: true,
+            ..Default::default()
+        };
+
+        let result = construct_ast_with(&mut Lexer::new(expression), &options);
+
+        assert_eq!(result.unwrap_err().to_string(), error);
+        assert!(construct_ast_with(&mut Lexer::new("~p ^ q"), &options).is_err());
+        assert!(construct_ast_with(&mut Lexer::new("p ^ q"), &options).is_ok());
+    }
+
+    #[test]
+    fn test_construct_ast_long_chain_depth_limit() {
+        let chain = vec!["~p"; 2_000].join(" v ");
+
+        let result = construct_ast_with(
+            &mut Lexer::new(&chain),
+            &ParseOptions {
+                free_variables: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Expression too deeply nested at 1:4999"
+        );
+        let negations = format!("{}1", "~".repeat(10_000));
+        assert_eq!(
+            construct_ast(&mut Lexer::new(&negations))
+                .unwrap_err()
+                .to_string(),
+            "Expression too deeply nested"
+        );
+    }
+
+    #[test]
+    fn test_construct_ast_equivalence_is_left_associative() {
+        let (results, _) = construct_ast(&mut Lexer::new("1 <=> 0 <=> 1")).unwrap();
+
+        let mut inner = ASTNode::new(Token::Operator(Operator::Equivalence));
+        inner.add_left_token(Token::Value(Value::Bool(true)));
+        inner.add_right_token(Token::Value(Value::Bool(false)));
+        let expected = ASTNode {
+            token: Token::Operator(Operator::Equivalence),
+            left: Some(Box::new(inner)),
+            right: Some(Box::new(ASTNode::new(Token::Value(Value::Bool(true))))),
+        };
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_construct_ast_strict_rejects_mixed_arrows() {
+        let strict = ParseOptions {
+            free_variables: true,
+            strict: true,
+            ..Default::default()
+        };
+        let parse = |expr: &str| construct_ast_with(&mut Lexer::new(expr), &strict);
+
+        assert_eq!(
+            parse("a => b <=> c").unwrap_err().to_string(),
+            "Operators '=>' and '<=>' mixed without parentheses at 1:8"
+        );
+        assert!(parse("(a <=> b) => c => (a => c <=> b)").is_err());
+        assert!(parse("(a => b) <=> c").is_ok());
+        assert!(parse("a <=> b <=> (c => a => b)").is_ok());
+    }
+
+    #[test]
+    fn test_construct_ast_circular_assignment() {
+        let free = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let result = construct_ast_with(&mut Lexer::new("a := b  b := a  a"), &free);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Variable b is used in the assignment of a before being assigned"
+        );
+        assert!(construct_ast(&mut Lexer::new("a := b  b := a  a")).is_err());
+        assert!(construct_ast_with(&mut Lexer::new("a := a  a"), &free).is_err());
+        assert!(construct_ast(&mut Lexer::new("a := 1 ^  a")).is_err());
+    }
+
+    #[test]
+    fn test_construct_ast_overrides_win_over_assignments() {
+        let options = ParseOptions {
+            overrides: HashMap::from([("p".to_string(), false)]),
+            ..Default::default()
+        };
+
+        let (_, vars) = construct_ast_with(&mut Lexer::new("p := 1 q := p q"), &options).unwrap();
+        assert_eq!(
+            vars,
+            HashMap::from([("p".to_string(), false), ("q".to_string(), false)])
+        );
+
+        let statements = parse_program_with("q := ~p; r := q; p ^ r", &options).unwrap();
+        assert_eq!(statements[0].1.get("r"), Some(&true));
+    }
+
+    #[test]
+    fn test_parse_program_carries_variables() {
+        let statements = parse_program("p := 1; p ^ 0; p v 0").unwrap();
+
+        let results: Vec<String> = statements
+            .iter()
+            .map(|(root, _)| root.to_expr_string())
+            .collect();
+        assert_eq!(results, vec!["p ^ 0", "p v 0"]);
+        for (_, vars) in &statements {
+            assert_eq!(vars.get("p"), Some(&true));
+        }
+    }
+
+    #[test]
+    fn test_parse_program_reassigns_variables() {
+        let options = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let statements = parse_program_with("p := 1 p; p := 0 p ^ q;", &options).unwrap();
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[0].1.get("p"), Some(&true));
+        assert_eq!(statements[1].1.get("p"), Some(&false));
+    }
+
+    #[test]
+    fn test_construct_program_records_assignment_reads() {
+        let program = construct_program("p := 1 q := p ~q; r := q ^ 1 0", &Default::default());
+
+        let program = program.unwrap();
+        assert_eq!(program.statements.len(), 2);
+        assert_eq!(
+            program.assignment_reads.into_iter().collect::<Vec<_>>(),
+            vec!["p", "q"]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_should_fail_on_undefined() {
+        assert!(parse_program("p := 1; q ^ p").is_err());
+        assert!(parse_program("p := 1;").is_err());
+        assert!(construct_ast(&mut Lexer::new("1; 0")).is_err());
+    }
+}
+
+/// Printing the AST back to infix and parsing it again has to give the same tree,
+/// which guards the precedence handling of both the parser and the printer
+#[cfg(test)]
+mod round_trip_tests {
+    use rstest::rstest;
+
+    use super::{construct_ast_with, ParseOptions};
+    use crate::lexer::Lexer;
+
+    #[rstest]
+    #[case("1 ^ 0")]
+    #[case("1 ^ 0 v 1")]
+    #[case("1 v 0 ^ 1")]
+    #[case("1 ^ (0 v 1)")]
+    #[case("(1 ^ 0) v 1")]
+    #[case("((1 ^ 0) v 1)")]
+    #[case("~1 v 0")]
+    #[case("~1 v ~0")]
+    #[case("~~1 ^ 0")]
+    #[case("0 ^ 1 v 0 ^ 1")]
+    #[case("~1 v ~0 <=> 0")]
+    #[case("1 <=> 0 v 1 ^ 0")]
+    #[case("p := 0 q := 1 ~p v ~q")]
+    #[case("p := 0 ~p v w")]
+    #[case("~(p ^ q) => (r <=> p + q)")]
+    #[case("p nand q ^ r v s nor p")]
+    #[case("p => q => r")]
+    #[case("(p => q) => r")]
+    fn test_print_parse_round_trip(#[case] expr: &str) {
+        let options = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let (ast, _) = construct_ast_with(&mut Lexer::new(expr), &options).unwrap();
+
+        let printed = ast.to_expr_string();
+        let (reparsed, _) = construct_ast_with(&mut Lexer::new(&printed), &options).unwrap();
+
+        assert_eq!(reparsed, ast, "{} printed as {}", expr, printed);
+    }
 }
 
 // This is my own alternative implementation of parser that built the AST
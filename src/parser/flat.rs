@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Result};
+
+use crate::lexer::{Operator, Token, Value};
+
+use super::ASTNode;
+
+/// Tree where chains of `^` and `v` are flattened into a single node,
+/// so that `p ^ q ^ r` is one conjunction of three operands instead of two nested ones
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FlatNode {
+    Value(Value),
+    Not(Box<FlatNode>),
+    /// `And` or `Or` over all the operands of a chain of the same operator
+    Chain(Operator, Vec<FlatNode>),
+    /// Any other infix operator, kept binary
+    Binary(Operator, Box<FlatNode>, Box<FlatNode>),
+}
+
+impl ASTNode {
+    /// Flattens nested `^` and `v` into n-ary nodes, keeping the operands in order.
+    /// Like the evaluator, it fails for an operator missing an operand.
+    pub fn flatten_associative(self) -> Result<FlatNode> {
+        let token = self.token;
+        let flatten = |node: Option<Box<ASTNode>>| match node {
+            Some(node) => node.flatten_associative(),
+            None => Err(anyhow!("Operator '{}' missing operand", token)),
+        };
+        Ok(match &token {
+            Token::Value(value) => FlatNode::Value(value.clone()),
+            Token::Operator(Operator::Not) => FlatNode::Not(Box::new(flatten(self.left)?)),
+            Token::Operator(op @ (Operator::And | Operator::Or)) => {
+                let mut operands = vec![];
+                for operand in [flatten(self.left)?, flatten(self.right)?] {
+                    match operand {
+                        FlatNode::Chain(inner, inner_operands) if inner == *op => {
+                            operands.extend(inner_operands)
+                        }
+                        other => operands.push(other),
+                    }
+                }
+                FlatNode::Chain(op.clone(), operands)
+            }
+            Token::Operator(op) => FlatNode::Binary(
+                op.clone(),
+                Box::new(flatten(self.left)?),
+                Box::new(flatten(self.right)?),
+            ),
+        })
+    }
+}
+
+impl FlatNode {
+    /// Binary tree of the node, with chains grouped to the left like the parser does.
+    /// A chain of a single operand is that operand, and an empty one is
+    /// the identity of its operator: `1` for `^` and `0` for `v`.
+    pub fn to_ast(self) -> ASTNode {
+        match self {
+            FlatNode::Value(value) => ASTNode::new(Token::Value(value)),
+            FlatNode::Not(operand) => operand
+                .to_ast()
+                .make_new_root_left(Token::Operator(Operator::Not)),
+            FlatNode::Chain(op, operands) => operands
+                .into_iter()
+                .map(FlatNode::to_ast)
+                .reduce(|left, right| binary(op.clone(), left, right))
+                .unwrap_or_else(|| ASTNode::new(Token::Value(Value::Bool(op == Operator::And)))),
+            FlatNode::Binary(op, left, right) => binary(op, left.to_ast(), right.to_ast()),
+        }
+    }
+}
+
+fn binary(op: Operator, left: ASTNode, right: ASTNode) -> ASTNode {
+    ASTNode {
+        token: Token::Operator(op),
+        left: Some(Box::new(left)),
+        right: Some(Box::new(right)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::{Operator, Token, Value};
+    use crate::parser::{parse, ASTNode};
+
+    use super::FlatNode;
+
+    #[test]
+    fn test_flatten_chain_and_back() {
+        let root = parse("1 ^ 0 ^ 1");
+
+        let flat = root.clone().flatten_associative().unwrap();
+
+        let expected = FlatNode::Chain(
+            Operator::And,
+            vec![
+                FlatNode::Value(Value::Bool(true)),
+                FlatNode::Value(Value::Bool(false)),
+                FlatNode::Value(Value::Bool(true)),
+            ],
+        );
+        assert_eq!(flat, expected);
+        assert_eq!(flat.to_ast(), root);
+    }
+
+    #[test]
+    fn test_flatten_stops_at_other_operators() {
+        let root = parse("p ^ (q ^ r) ^ ~(p ^ q) ^ (p v q v r) ^ (p => q)");
+
+        let flat = root.clone().flatten_associative().unwrap();
+
+        match &flat {
+            FlatNode::Chain(Operator::And, operands) => {
+                assert_eq!(operands.len(), 6);
+                assert!(matches!(&operands[3], FlatNode::Not(_)));
+                assert!(matches!(&operands[4], FlatNode::Chain(Operator::Or, or) if or.len() == 3));
+                assert!(matches!(
+                    &operands[5],
+                    FlatNode::Binary(Operator::Implication, _, _)
+                ));
+            }
+            other => panic!("Expected a conjunction, got {:?}", other),
+        }
+        // Regrouped to the left, but the same formula
+        assert_eq!(
+            flat.to_ast(),
+            parse("p ^ q ^ r ^ ~(p ^ q) ^ (p v q v r) ^ (p => q)")
+        );
+    }
+
+    #[test]
+    fn test_flatten_missing_operand() {
+        let mut root = parse("p v q");
+        root.left = Some(Box::new(ASTNode::new(Token::Operator(Operator::Not))));
+
+        assert_eq!(
+            root.flatten_associative().unwrap_err().to_string(),
+            "Operator 'Not' missing operand"
+        );
+    }
+
+    #[test]
+    fn test_empty_chain_is_identity() {
+        assert_eq!(FlatNode::Chain(Operator::And, vec![]).to_ast(), parse("1"));
+        assert_eq!(FlatNode::Chain(Operator::Or, vec![]).to_ast(), parse("0"));
+    }
+}
@@ -1,13 +1,88 @@
-use anyhow::Result;
-use std::{collections::{VecDeque, HashMap}, fmt, fs::File, io::Write, path::Path};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
+use std::io::ErrorKind;
+use std::process::{Command, Stdio};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::File,
+    io::Write,
+    path::Path,
+};
 
-use crate::lexer::Token;
+use crate::interpreter::fold_node;
+use crate::lexer::{Operator, Token, Value};
 mod construct;
-pub use construct::construct_ast;
+mod flat;
+mod normal_form;
+mod prefix;
+mod trace;
+pub use construct::{
+    construct_ast, construct_ast_with, construct_program, parse_program, parse_program_with,
+    ParseOptions, Precedence, Program, MAX_DEPTH,
+};
+pub use flat::FlatNode;
+pub use normal_form::{extract_clauses, Clauses, Literal};
+pub use prefix::{construct_ast_prefix, construct_ast_prefix_with};
+pub use trace::{construct_ast_traced, replay, ParseStep, ParseTrace};
 
+/// Values of the variables by name, assigned with `:=` or supplied by the caller.
+/// Being a plain `HashMap`, it's built with `StoredVariables::new()` and `insert`,
+/// collected from pairs, or made out of string slices with `stored_variables`.
 pub type StoredVariables = HashMap<String, bool>;
 
-#[derive(Debug, PartialEq)]
+/// Variables with the given values, e.g. `stored_variables([("p", true), ("q", false)])`
+pub fn stored_variables<'a>(values: impl IntoIterator<Item = (&'a str, bool)>) -> StoredVariables {
+    values
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+}
+
+/// Names of the assigned variables that the formula never refers to, in alphabetical order.
+/// Only the formula counts, a variable used just in the assignment of another one is unused;
+/// `Program::assignment_reads` holds the names read there.
+pub fn unused_assignments(vars: &StoredVariables, body: &ASTNode) -> Vec<String> {
+    let used = body.free_variables();
+    let mut unused: Vec<String> = vars
+        .keys()
+        .filter(|name| !used.contains(*name))
+        .cloned()
+        .collect();
+    unused.sort();
+    unused
+}
+
+/// Image formats the graph can be rendered to with Graphviz
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    /// Format matching the extension of the output file
+    pub fn from_path(path: &Path) -> Result<ImageFormat> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => Ok(ImageFormat::Svg),
+            Some("png") => Ok(ImageFormat::Png),
+            other => Err(anyhow!(
+                "Unsupported image format {}, expected .svg or .png",
+                other.unwrap_or("without extension")
+            )),
+        }
+    }
+
+    fn dot_flag(&self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "-Tsvg",
+            ImageFormat::Png => "-Tpng",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ASTNode {
     pub token: Token,
     pub left: Option<Box<ASTNode>>,
@@ -46,21 +121,19 @@ impl ASTNode {
     }
 
     pub fn make_new_root_left(self, token: Token) -> ASTNode {
-        let new_root = ASTNode {
+        ASTNode {
             token,
             left: Some(Box::new(self)),
             right: None,
-        };
-        new_root
+        }
     }
 
     pub fn make_new_root_right(self, token: Token) -> ASTNode {
-        let new_root = ASTNode {
+        ASTNode {
             token,
             left: None,
             right: Some(Box::new(self)),
-        };
-        new_root
+        }
     }
 
     pub fn add_left_child(&mut self, node: ASTNode) {
@@ -87,58 +160,780 @@ impl ASTNode {
         }));
     }
 
+    /// Calls the function on every node in pre-order: the node, then its left and right subtree
+    pub fn visit<F: FnMut(&ASTNode)>(&self, f: &mut F) {
+        f(self);
+        for child in [&self.left, &self.right].into_iter().flatten() {
+            child.visit(f);
+        }
+    }
+
+    /// Iterator over the nodes in the same pre-order as `visit`
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { stack: vec![self] }
+    }
+
+    /// Names of all the variables referenced in the formula
+    pub fn free_variables(&self) -> BTreeSet<String> {
+        self.iter()
+            .filter_map(|node| match &node.token {
+                Token::Value(Value::Variable(name)) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Number of nodes on the longest path from the root to a leaf
+    pub fn depth(&self) -> usize {
+        let left = self.left.as_ref().map_or(0, |node| node.depth());
+        let right = self.right.as_ref().map_or(0, |node| node.depth());
+        1 + left.max(right)
+    }
+
+    /// Number of all the nodes, operators and values
+    pub fn node_count(&self) -> usize {
+        let mut count = 0;
+        self.visit(&mut |_| count += 1);
+        count
+    }
+
+    /// Number of the operator nodes
+    pub fn operator_count(&self) -> usize {
+        let own = usize::from(matches!(self.token, Token::Operator(_)));
+        let left = self.left.as_ref().map_or(0, |node| node.operator_count());
+        let right = self.right.as_ref().map_or(0, |node| node.operator_count());
+        own + left + right
+    }
+
+    /// Operands of the top-level chain of conjunctions, e.g. clauses of a CNF
+    pub fn conjuncts(&self) -> Vec<&ASTNode> {
+        match (&self.token, &self.left, &self.right) {
+            (Token::Operator(Operator::And), Some(left), Some(right)) => {
+                let mut conjuncts = left.conjuncts();
+                conjuncts.extend(right.conjuncts());
+                conjuncts
+            }
+            _ => vec![self],
+        }
+    }
+
+    /// Replaces every occurrence of the variable with a copy of the replacement
+    pub fn substitute(self, var: &str, replacement: &ASTNode) -> ASTNode {
+        match &self.token {
+            Token::Value(Value::Variable(name)) if name == var => replacement.clone(),
+            _ => ASTNode {
+                token: self.token,
+                left: self
+                    .left
+                    .map(|node| Box::new(node.substitute(var, replacement))),
+                right: self
+                    .right
+                    .map(|node| Box::new(node.substitute(var, replacement))),
+            },
+        }
+    }
+
+    /// Renames every occurrence of the variable. Fails without changing anything
+    /// when the new name is already used in the formula, as that would merge two variables.
+    pub fn rename_var(&mut self, from: &str, to: &str) -> Result<()> {
+        if from != to && self.free_variables().contains(to) {
+            return Err(anyhow!(
+                "Cannot rename {} to {}, the formula already has variable {}",
+                from,
+                to,
+                to
+            ));
+        }
+        self.rename_leaves(from, to);
+        Ok(())
+    }
+
+    fn rename_leaves(&mut self, from: &str, to: &str) {
+        if let Token::Value(Value::Variable(name)) = &mut self.token {
+            if name == from {
+                *name = to.to_string();
+            }
+        }
+        for child in [&mut self.left, &mut self.right].into_iter().flatten() {
+            child.rename_leaves(from, to);
+        }
+    }
+
+    /// Same tree, token for token. This is what `==` compares as well,
+    /// so `p ^ q` isn't structurally equal to `q ^ p`.
+    pub fn structurally_eq(&self, other: &ASTNode) -> bool {
+        self == other
+    }
+
+    /// Sorts the operands of commutative operators, comparing the trees token by token,
+    /// so that formulas differing only in the order of such operands become equal
+    pub fn canonicalize(self) -> ASTNode {
+        let mut left = self.left.map(|node| Box::new(node.canonicalize()));
+        let mut right = self.right.map(|node| Box::new(node.canonicalize()));
+        if let (Token::Operator(op), Some(l), Some(r)) = (&self.token, &left, &right) {
+            if op.is_commutative() && l > r {
+                std::mem::swap(&mut left, &mut right);
+            }
+        }
+        ASTNode {
+            token: self.token,
+            left,
+            right,
+        }
+    }
+
+    /// Folds the subexpressions with constant operands using the identity and domination laws,
+    /// e.g. `1 ^ p` into `p` and `p v 1` into `1`. Variables are never compared
+    /// with each other, so `p ^ ~p` stays as it is. Operators missing an operand are kept.
+    pub fn fold_constants(self) -> ASTNode {
+        let left = self.left.map(|left| left.fold_constants());
+        let right = self.right.map(|right| right.fold_constants());
+        fold_node(self.token, left, right)
+    }
+
+    /// Renders the tree back into infix notation, parenthesizing an operand only when
+    /// it binds looser than its parent, or as loose on the side the parent doesn't group to,
+    /// e.g. `p ^ (q ^ r)` but `p => q => r`
+    pub fn to_expr_string(&self) -> String {
+        match &self.token {
+            Token::Value(Value::Bool(true)) => "1".to_string(),
+            Token::Value(Value::Bool(false)) => "0".to_string(),
+            Token::Value(Value::Variable(name)) => name.clone(),
+            Token::Operator(Operator::Not) => {
+                format!("~{}", operand_string(&self.left, &Operator::Not, false))
+            }
+            Token::Operator(op) => format!(
+                "{} {} {}",
+                operand_string(&self.left, op, false),
+                op.symbol(),
+                operand_string(&self.right, op, true)
+            ),
+        }
+    }
+
+    /// Prefix (Polish) notation, every operator written before its operands, e.g. `^ 1 v 0 1`
+    pub fn to_prefix(&self) -> String {
+        let symbols: Vec<&str> = self.iter().map(ASTNode::symbol).collect();
+        symbols.join(" ")
+    }
+
+    /// Postfix (reverse Polish) notation, every operator written after its operands,
+    /// e.g. `1 0 1 v ^`
+    pub fn to_postfix(&self) -> String {
+        let mut symbols = vec![];
+        self.collect_postfix(&mut symbols);
+        symbols.join(" ")
+    }
+
+    fn collect_postfix<'a>(&'a self, symbols: &mut Vec<&'a str>) {
+        for child in [&self.left, &self.right].into_iter().flatten() {
+            child.collect_postfix(symbols);
+        }
+        symbols.push(self.symbol());
+    }
+
+    fn symbol(&self) -> &str {
+        match &self.token {
+            Token::Value(Value::Bool(true)) => "1",
+            Token::Value(Value::Bool(false)) => "0",
+            Token::Value(Value::Variable(name)) => name,
+            Token::Operator(op) => op.symbol(),
+        }
+    }
+
+    /// Plain text drawing of the tree, a node per line below its parent, e.g.
+    ///
+    /// ```text
+    /// And
+    /// ├─ true
+    /// └─ false
+    /// ```
+    pub fn to_tree_string(&self) -> String {
+        let mut tree = format!("{}\n", self.token);
+        self.write_children(&mut tree, "");
+        tree
+    }
+
+    fn write_children(&self, tree: &mut String, indent: &str) {
+        let children: Vec<&ASTNode> = [&self.left, &self.right]
+            .into_iter()
+            .flatten()
+            .map(|child| child.as_ref())
+            .collect();
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let (connector, continuation) = if last {
+                ("└─ ", "   ")
+            } else {
+                ("├─ ", "│  ")
+            };
+            tree.push_str(&format!("{}{}{}\n", indent, connector, child.token));
+            child.write_children(tree, &format!("{}{}", indent, continuation));
+        }
+    }
+
     /// Outputs graph in graphviz format
     /// Check https://graphviz.org/pdf/dotguide.pdf
     pub fn visualize_graph(&self, out_path: &Path) -> Result<()> {
-        fn write_definition(counter: u32, token: &Token) -> String {
+        let mut file = File::create(out_path)?;
+        file.write_all(self.to_dot(None).as_bytes())?;
+        Ok(())
+    }
+
+    /// Same graph as `visualize_graph`, with the evaluated result added to the root label,
+    /// so that the diagram agrees with the printed result
+    pub fn visualize_graph_with_result(&self, out_path: &Path, result: bool) -> Result<()> {
+        let mut file = File::create(out_path)?;
+        file.write_all(self.to_dot(Some(result)).as_bytes())?;
+        Ok(())
+    }
+
+    /// Nodes in breadth-first order, numbered by their index starting with the root as 0,
+    /// and the edges between them as (parent, child) pairs of those numbers
+    fn graph_nodes_and_edges(&self) -> (Vec<&ASTNode>, Vec<(u32, u32)>) {
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        let mut queue = VecDeque::from([(None, self)]);
+        while let Some((parent, node)) = queue.pop_front() {
+            let id = nodes.len() as u32;
+            nodes.push(node);
+            if let Some(parent) = parent {
+                edges.push((parent, id));
+            }
+            for child in [&node.left, &node.right].into_iter().flatten() {
+                queue.push_back((Some(id), child.as_ref()));
+            }
+        }
+        (nodes, edges)
+    }
+
+    /// Renders the graph into an image with the Graphviz `dot` binary, which has to be installed
+    pub fn render_image(&self, out_path: &Path, format: ImageFormat) -> Result<()> {
+        let mut dot = match Command::new("dot")
+            .arg(format.dot_flag())
+            .arg("-o")
+            .arg(out_path)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(dot) => dot,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(anyhow!(
+                    "Graphviz dot command not found, install Graphviz to render images"
+                ))
+            }
+            Err(e) => return Err(anyhow!("Cannot run Graphviz dot: {}", e)),
+        };
+        if let Some(mut stdin) = dot.stdin.take() {
+            stdin.write_all(self.to_dot(None).as_bytes())?;
+        }
+        let output = dot.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Graphviz dot failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_dot(&self, result: Option<bool>) -> String {
+        fn write_definition(counter: u32, token: &Token, label: &str) -> String {
             match token {
-                Token::Value(_) => format!("    {} [label=\"{}\"]\n", counter, token),
-                Token::Operator(_) => {
-                    format!("    {} [label=\"{}\" shape=\"box\"]\n", counter, token)
-                }
+                Token::Value(_) => format!("    {} [label=\"{}\"]\n", counter, label),
+                Token::Operator(op) => format!(
+                    "    {} [label=\"{}\" shape=\"box\" style=filled fillcolor=\"{}\"]\n",
+                    counter,
+                    label,
+                    fill_color(op)
+                ),
             }
         }
 
-        let mut queue = VecDeque::new();
-        let mut graph_relations = vec![];
+        let (nodes, edges) = self.graph_nodes_and_edges();
         let mut graph = vec!["graph G {\n".to_string()];
-        let mut counter: u32 = 0;
-        graph.push(write_definition(counter, &self.token));
-
-        queue.push_back((counter, Box::new(self)));
-
-        loop {
-            match queue.pop_front() {
-                Some((num, node)) => {
-                    if counter > 0 {
-                        graph.push(write_definition(counter, &node.token));
-                        graph_relations.push(format!("    {} -- {}\n", num, counter));
-                    }
-                    if let Some(left) = &node.left {
-                        match left.token {
-                            Token::Operator(_) => queue.push_back((counter, Box::new(&left))),
-                            Token::Value(_) => queue.push_back((counter, Box::new(&left))),
-                        };
-                    };
-                    if let Some(right) = &node.right {
-                        match right.token {
-                            Token::Operator(_) => queue.push_back((counter, Box::new(&right))),
-                            Token::Value(_) => queue.push_back((counter, Box::new(&right))),
-                        };
-                    };
-                }
-                None => break,
-            }
-            counter += 1;
+        for (id, node) in nodes.into_iter().enumerate() {
+            let label = match result {
+                Some(result) if id == 0 => format!("{} = {}", node.token, result),
+                _ => node.token.to_string(),
+            };
+            graph.push(write_definition(id as u32, &node.token, &label));
         }
-        let mut file = File::create(out_path)?;
-        for definition in graph {
-            file.write(definition.as_bytes())?;
+        let graph_relations: Vec<String> = edges
+            .iter()
+            .map(|(parent, child)| format!("    {} -- {}\n", parent, child))
+            .collect();
+        let mut dot = graph.concat();
+        dot.push_str(&graph_relations.concat());
+        dot.push('}');
+        dot
+    }
+}
+
+/// Pre-order iterator over the nodes of a tree, see `ASTNode::iter`
+pub struct Iter<'a> {
+    stack: Vec<&'a ASTNode>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a ASTNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        // Right goes first, so that the left subtree is popped before it
+        for child in [&node.right, &node.left].into_iter().flatten() {
+            self.stack.push(child);
         }
-        for relation in graph_relations {
-            file.write(relation.as_bytes())?;
+        Some(node)
+    }
+}
+
+/// Fill of the operator nodes in the graph, so that large trees are easier to follow
+fn fill_color(op: &Operator) -> &'static str {
+    match op {
+        Operator::And => "lightblue",
+        Operator::Or => "palegreen",
+        Operator::Not => "lightpink",
+        Operator::Implication => "khaki",
+        Operator::Equivalence => "plum",
+        Operator::Xor => "lightsalmon",
+        Operator::Nand => "lightcyan",
+        Operator::Nor => "wheat",
+        _ => "white",
+    }
+}
+
+fn operand_string(operand: &Option<Box<ASTNode>>, parent: &Operator, is_right: bool) -> String {
+    match operand {
+        Some(node) => parenthesized(&node.token, node.to_expr_string(), parent, is_right),
+        None => String::new(),
+    }
+}
+
+/// Operand already rendered, in parentheses when its operator binds looser than the parent,
+/// or as loose on the side the parent doesn't group to
+pub(crate) fn parenthesized(
+    token: &Token,
+    rendered: String,
+    parent: &Operator,
+    is_right: bool,
+) -> String {
+    let groups_to_operand = parent.is_right_associative() == is_right;
+    match token {
+        Token::Operator(op)
+            if *op != Operator::Not && (op < parent || (op == parent && !groups_to_operand)) =>
+        {
+            format!("({})", rendered)
         }
-        file.write("}".as_bytes())?;
-        Ok(())
+        _ => rendered,
+    }
+}
+
+/// Joins two formulas with an infix connective
+pub fn combine(left: ASTNode, op: Operator, right: ASTNode) -> Result<ASTNode> {
+    if !op.is_binary() {
+        return Err(anyhow!(
+            "Cannot combine formulas with non-binary operator {:?}",
+            op
+        ));
+    }
+    Ok(ASTNode {
+        token: Token::Operator(op),
+        left: Some(Box::new(left)),
+        right: Some(Box::new(right)),
+    })
+}
+
+pub fn negate_formula(node: ASTNode) -> ASTNode {
+    node.make_new_root_left(Token::Operator(Operator::Not))
+}
+
+/// Tree of the formula with its variables left free, shared by the tests across the crate
+#[cfg(test)]
+pub(crate) fn parse(expr: &str) -> ASTNode {
+    let options = ParseOptions {
+        free_variables: true,
+        ..Default::default()
+    };
+    construct_ast_with(&mut crate::lexer::Lexer::new(expr), &options)
+        .unwrap()
+        .0
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use super::{
+        combine, construct_ast, construct_ast_with, negate_formula, parse, stored_variables,
+        unused_assignments, ASTNode, ImageFormat, ParseOptions, StoredVariables,
+    };
+    use crate::interpreter::{evaluate, evaluate_ref};
+    use crate::lexer::{Lexer, Operator, Token, Value};
+
+    #[test]
+    fn test_stored_variables_built_by_hand() {
+        let (root, _) = construct_ast_with(
+            &mut Lexer::new("p ^ ~q"),
+            &ParseOptions {
+                free_variables: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut variables = StoredVariables::new();
+        variables.insert("p".to_string(), true);
+        variables.insert("q".to_string(), false);
+
+        assert!(evaluate(root.clone(), &variables).unwrap());
+        assert_eq!(stored_variables([("p", true), ("q", false)]), variables);
+        assert!(!evaluate(root, &stored_variables([("p", true), ("q", true)])).unwrap());
+    }
+
+    #[test]
+    fn test_unused_assignments() {
+        let (root, vars) = construct_ast(&mut Lexer::new("p := 1 q := 0 p ^ 1")).unwrap();
+
+        assert_eq!(unused_assignments(&vars, &root), vec!["q"]);
+
+        let (root, vars) = construct_ast(&mut Lexer::new("p := 1 q := 0 r := 1 p ^ q")).unwrap();
+        assert_eq!(unused_assignments(&vars, &root), vec!["r"]);
+    }
+
+    #[test]
+    fn test_combine_parsed_formulas() {
+        let (left, _) = construct_ast(&mut Lexer::new("1 v 0")).unwrap();
+        let (right, _) = construct_ast(&mut Lexer::new("~1")).unwrap();
+
+        let result = combine(left, Operator::And, right).unwrap();
+
+        let (expected, _) = construct_ast(&mut Lexer::new("(1 v 0) ^ ~1")).unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_combine_should_fail_on_unary_operator() {
+        let left = ASTNode::new(Token::Value(Value::Bool(true)));
+        let right = ASTNode::new(Token::Value(Value::Bool(false)));
+
+        let result = combine(left, Operator::Not, right);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_conjuncts_of_cnf() {
+        let (node, _) = construct_ast(&mut Lexer::new("(1 v 0) ^ ~1 ^ (0 v 0)")).unwrap();
+
+        let result: Vec<String> = node
+            .conjuncts()
+            .iter()
+            .map(|c| c.token.to_string())
+            .collect();
+
+        assert_eq!(result, vec!["Or", "Not", "Or"]);
+    }
+
+    #[test]
+    fn test_free_variables_of_nested_expression() {
+        let mut lexer = Lexer::new("p := 1 q := 0 r := 1 s := 0 (p v (q ^ (r => ~s))) <=> ~s");
+        let (node, _) = construct_ast(&mut lexer).unwrap();
+
+        let result: Vec<String> = node.free_variables().into_iter().collect();
+
+        assert_eq!(result, vec!["p", "q", "r", "s"]);
+    }
+
+    #[test]
+    fn test_free_variables_skip_only_assigned() {
+        let mut lexer = Lexer::new("q := 0 p := 1 r := 1 ~(p ^ (q v ~p)) => q ^ p");
+        let (node, _) = construct_ast(&mut lexer).unwrap();
+
+        let result: Vec<String> = node.free_variables().into_iter().collect();
+
+        assert_eq!(result, vec!["p", "q"]);
+        assert!(construct_ast(&mut Lexer::new("1 ^ ~0"))
+            .unwrap()
+            .0
+            .free_variables()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_visit_in_pre_order() {
+        let node = parse("1 ^ (0 v 1)");
+
+        let mut visited = vec![];
+        node.visit(&mut |node| visited.push(node.token.to_string()));
+        let iterated: Vec<String> = node.iter().map(|node| node.token.to_string()).collect();
+
+        assert_eq!(visited, vec!["And", "true", "Or", "false", "true"]);
+        assert_eq!(iterated, visited);
+    }
+
+    #[test]
+    fn test_size_metrics() {
+        let not = parse("~1");
+        assert_eq!(not.depth(), 2);
+        assert_eq!(not.node_count(), 2);
+        assert_eq!(not.operator_count(), 1);
+
+        let nested = parse("1 ^ (0 v 1)");
+        assert_eq!(nested.depth(), 3);
+        assert_eq!(nested.node_count(), 5);
+        assert_eq!(nested.operator_count(), 2);
+
+        assert_eq!(parse("p").depth(), 1);
+    }
+
+    #[test]
+    fn test_substitute_variable() {
+        let result = parse("p v ~p").substitute("p", &parse("a ^ b"));
+
+        assert_eq!(result, parse("(a ^ b) v ~(a ^ b)"));
+        let vars = HashMap::from([("a".to_string(), true), ("b".to_string(), false)]);
+        assert!(evaluate_ref(&result, &vars).unwrap());
+        assert_eq!(
+            parse("q => r").substitute("p", &parse("1")),
+            parse("q => r")
+        );
+    }
+
+    #[test]
+    fn test_rename_var() {
+        let mut node = parse("p ^ (p v q)");
+
+        node.rename_var("p", "x").unwrap();
+
+        assert_eq!(node, parse("x ^ (x v q)"));
+    }
+
+    #[test]
+    fn test_rename_var_should_fail_on_existing_name() {
+        let mut node = parse("p ^ (p v q)");
+
+        assert!(node.rename_var("p", "q").is_err());
+        assert_eq!(node, parse("p ^ (p v q)"));
+        assert!(node.rename_var("p", "p").is_ok());
+    }
+
+    #[test]
+    fn test_canonicalize_commutative_operands() {
+        let left = parse("p ^ q");
+        let right = parse("q ^ p");
+        assert!(!left.structurally_eq(&right));
+
+        let canonical = |node: ASTNode| node.canonicalize();
+        assert!(canonical(left).structurally_eq(&canonical(right)));
+        assert_eq!(
+            canonical(parse("(s <=> r) v ~(q + p)")),
+            canonical(parse("~(p + q) v (r <=> s)"))
+        );
+        assert_eq!(canonical(parse("q => p")), parse("q => p"));
+    }
+
+    #[test]
+    fn test_canonicalize_orders_operands_by_tree() {
+        assert_eq!(parse("q ^ p").canonicalize(), parse("p ^ q"));
+        assert_eq!(parse("~p v 1").canonicalize(), parse("1 v ~p"));
+        assert_eq!(parse("(q v p) + r").canonicalize(), parse("r + (p v q)"));
+    }
+
+    #[test]
+    fn test_fold_constants_to_variable() {
+        let result = parse("1 ^ (0 v p)").fold_constants();
+
+        assert_eq!(
+            result,
+            ASTNode::new(Token::Value(Value::Variable("p".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_fold_constants_laws() {
+        assert_eq!(parse("~1").fold_constants(), parse("0"));
+        assert_eq!(parse("p ^ 0").fold_constants(), parse("0"));
+        assert_eq!(parse("0 v p").fold_constants(), parse("p"));
+        assert_eq!(
+            parse("(p => 0) v (q <=> 1)").fold_constants(),
+            parse("~p v q")
+        );
+    }
+
+    #[test]
+    fn test_fold_constants_leaves_contradiction() {
+        let node = parse("p ^ ~p");
+
+        assert_eq!(node.clone().fold_constants(), node);
+    }
+
+    #[test]
+    fn test_fold_constants_keeps_missing_operand() {
+        let mut node = ASTNode::new(Token::Operator(Operator::And));
+        node.add_left_token(Token::Operator(Operator::Not));
+        node.left
+            .as_mut()
+            .unwrap()
+            .add_left_token(Token::Value(Value::Bool(true)));
+
+        let expected = ASTNode {
+            token: Token::Operator(Operator::And),
+            left: Some(Box::new(parse("0"))),
+            right: None,
+        };
+        assert_eq!(node.fold_constants(), expected);
+    }
+
+    #[test]
+    fn test_to_expr_string() {
+        let (node, _) = construct_ast(&mut Lexer::new("~(1 ^ 0) v ~~1 => 0")).unwrap();
+
+        assert_eq!(node.to_expr_string(), "~(1 ^ 0) v ~~1 => 0");
+    }
+
+    #[test]
+    fn test_to_expr_string_minimal_parentheses() {
+        assert_eq!(parse("((1 ^ 0) v 1)").to_expr_string(), "1 ^ 0 v 1");
+        assert_eq!(parse("1 ^ (0 v 1)").to_expr_string(), "1 ^ (0 v 1)");
+        assert_eq!(parse("~(p) + (~q)").to_expr_string(), "~p + ~q");
+        assert_eq!(
+            parse("p nand (q nor r)").to_expr_string(),
+            "p nand (q nor r)"
+        );
+    }
+
+    #[rstest]
+    #[case("(p ^ q) ^ r", "p ^ q ^ r")]
+    #[case("p ^ (q ^ r)", "p ^ (q ^ r)")]
+    #[case("(p v q) v (r v p)", "p v q v (r v p)")]
+    #[case("p => (q => r)", "p => q => r")]
+    #[case("(p => q) => r", "(p => q) => r")]
+    fn test_to_expr_string_follows_associativity(#[case] expr: &str, #[case] expected: &str) {
+        let node = parse(expr);
+
+        assert_eq!(node.to_expr_string(), expected);
+        assert_eq!(parse(expected), node);
+    }
+
+    #[test]
+    fn test_to_prefix_and_postfix() {
+        let node = parse("1 ^ (0 v 1)");
+
+        assert_eq!(node.to_prefix(), "^ 1 v 0 1");
+        assert_eq!(node.to_postfix(), "1 0 1 v ^");
+
+        let node = parse("~(p => q) nand r");
+        assert_eq!(node.to_prefix(), "nand ~ => p q r");
+        assert_eq!(node.to_postfix(), "p q => ~ r nand");
+    }
+
+    #[test]
+    fn test_to_tree_string() {
+        let tree = parse("1 ^ (0 v ~1)").to_tree_string();
+
+        assert_eq!(
+            tree,
+            "And\n├─ true\n└─ Or\n   ├─ false\n   └─ Not\n      └─ true\n"
+        );
+        assert_eq!(
+            parse("(p => q) v r").to_tree_string().lines().nth(2),
+            Some("│  ├─ p")
+        );
+    }
+
+    #[test]
+    fn test_to_dot_root_label_with_result() {
+        let (node, _) = construct_ast(&mut Lexer::new("1 v 0")).unwrap();
+
+        let dot = node.to_dot(Some(true));
+
+        assert!(dot.starts_with(
+            "graph G {\n    0 [label=\"Or = true\" shape=\"box\" style=filled fillcolor=\"palegreen\"]\n"
+        ));
+        assert!(dot.contains("    1 [label=\"true\"]\n"));
+        assert!(dot.ends_with("    0 -- 1\n    0 -- 2\n}"));
+    }
+
+    #[test]
+    fn test_graph_edges_of_asymmetric_tree() {
+        let node = parse("~1 v (0 ^ 1)");
+
+        let (nodes, edges) = node.graph_nodes_and_edges();
+
+        let labels: Vec<String> = nodes.iter().map(|node| node.token.to_string()).collect();
+        assert_eq!(labels, vec!["Or", "Not", "And", "true", "false", "true"]);
+        assert_eq!(edges, vec![(0, 1), (0, 2), (1, 3), (2, 4), (2, 5)]);
+    }
+
+    #[test]
+    fn test_to_dot_operator_colors() {
+        let dot = parse("~(p ^ q) => (p v q <=> r)").to_dot(None);
+
+        for (label, color) in [
+            ("Not", "lightpink"),
+            ("And", "lightblue"),
+            ("Implication", "khaki"),
+            ("Or", "palegreen"),
+            ("Equivalence", "plum"),
+        ] {
+            assert!(
+                dot.contains(&format!(
+                    "[label=\"{}\" shape=\"box\" style=filled fillcolor=\"{}\"]",
+                    label, color
+                )),
+                "{} should be filled with {}",
+                label,
+                color
+            );
+        }
+        assert!(dot.contains("[label=\"p\"]\n"));
+    }
+
+    #[test]
+    fn test_to_dot_value_root_with_result() {
+        let (node, _) = construct_ast(&mut Lexer::new("0")).unwrap();
+
+        assert_eq!(
+            node.to_dot(Some(false)),
+            "graph G {\n    0 [label=\"false = false\"]\n}"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_ast_json_round_trip() {
+        let node = parse("~(p ^ 1) => q nor p");
+
+        let json = serde_json::to_string(&node).unwrap();
+        let restored: ASTNode = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored, node);
+    }
+
+    #[test]
+    fn test_image_format_from_extension() {
+        assert_eq!(
+            ImageFormat::from_path(Path::new("out.svg")).unwrap(),
+            ImageFormat::Svg
+        );
+        assert_eq!(
+            ImageFormat::from_path(Path::new("dir/out.png")).unwrap(),
+            ImageFormat::Png
+        );
+        assert!(ImageFormat::from_path(Path::new("out.jpg")).is_err());
+        assert!(ImageFormat::from_path(Path::new("out")).is_err());
+    }
+
+    #[test]
+    fn test_negate_formula() {
+        let (node, _) = construct_ast(&mut Lexer::new("1 ^ 0")).unwrap();
+
+        let result = negate_formula(node);
+
+        let (expected, _) = construct_ast(&mut Lexer::new("~(1 ^ 0)")).unwrap();
+        assert_eq!(result, expected);
     }
 }
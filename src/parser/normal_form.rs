@@ -0,0 +1,633 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+use crate::interpreter::{descend, EvalOptions};
+use crate::lexer::{Operator, Token, Value};
+
+use super::ASTNode;
+
+/// Variable or its negation, the building block of CNF clauses
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Literal {
+    pub var: String,
+    pub negated: bool,
+}
+
+impl Literal {
+    pub fn complement(&self) -> Literal {
+        Literal {
+            var: self.var.clone(),
+            negated: !self.negated,
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "~{}", self.var)
+        } else {
+            write!(f, "{}", self.var)
+        }
+    }
+}
+
+fn binary(operator: Operator, left: ASTNode, right: ASTNode) -> ASTNode {
+    ASTNode {
+        token: Token::Operator(operator),
+        left: Some(Box::new(left)),
+        right: Some(Box::new(right)),
+    }
+}
+
+fn missing_operand(node: &ASTNode) -> anyhow::Error {
+    anyhow!("Operator '{}' missing operand", node.token)
+}
+
+/// NNF of the node, or of its negation when `negated` is set,
+/// with `depth` levels left for the node and its operands
+fn nnf(node: &ASTNode, negated: bool, depth: usize) -> Result<ASTNode> {
+    let depth = descend(depth)?;
+    let operands = || match (node.left.as_deref(), node.right.as_deref()) {
+        (Some(left), Some(right)) => Ok((left, right)),
+        _ => Err(missing_operand(node)),
+    };
+    let (and, or) = if negated {
+        (Operator::Or, Operator::And)
+    } else {
+        (Operator::And, Operator::Or)
+    };
+    Ok(match &node.token {
+        Token::Value(Value::Bool(value)) => {
+            ASTNode::new(Token::Value(Value::Bool(value != &negated)))
+        }
+        Token::Value(Value::Variable(_)) if negated => node
+            .clone()
+            .make_new_root_left(Token::Operator(Operator::Not)),
+        Token::Value(Value::Variable(_)) => node.clone(),
+        Token::Operator(Operator::Not) => match node.left.as_deref() {
+            Some(operand) => nnf(operand, !negated, depth)?,
+            None => return Err(missing_operand(node)),
+        },
+        Token::Operator(Operator::And) => {
+            let (left, right) = operands()?;
+            binary(and, nnf(left, negated, depth)?, nnf(right, negated, depth)?)
+        }
+        Token::Operator(Operator::Nand) => {
+            let (left, right) = operands()?;
+            binary(
+                or,
+                nnf(left, !negated, depth)?,
+                nnf(right, !negated, depth)?,
+            )
+        }
+        Token::Operator(Operator::Or) => {
+            let (left, right) = operands()?;
+            binary(or, nnf(left, negated, depth)?, nnf(right, negated, depth)?)
+        }
+        Token::Operator(Operator::Nor) => {
+            let (left, right) = operands()?;
+            binary(
+                and,
+                nnf(left, !negated, depth)?,
+                nnf(right, !negated, depth)?,
+            )
+        }
+        // a => b is ~a v b
+        Token::Operator(Operator::Implication) => {
+            let (left, right) = operands()?;
+            binary(or, nnf(left, !negated, depth)?, nnf(right, negated, depth)?)
+        }
+        // a <=> b is (~a v b) ^ (a v ~b), while its negation a + b is (a v b) ^ (~a v ~b)
+        Token::Operator(op @ (Operator::Equivalence | Operator::Xor)) => {
+            let (left, right) = operands()?;
+            let equivalence = (*op == Operator::Equivalence) != negated;
+            binary(
+                Operator::And,
+                binary(
+                    Operator::Or,
+                    nnf(left, equivalence, depth)?,
+                    nnf(right, false, depth)?,
+                ),
+                binary(
+                    Operator::Or,
+                    nnf(left, !equivalence, depth)?,
+                    nnf(right, true, depth)?,
+                ),
+            )
+        }
+        Token::Operator(_) => node.clone(),
+    })
+}
+
+/// Distributes `inner` over `outer` in a tree in NNF, so that no `inner`
+/// is left above an `outer`. With `^` as outer and `v` as inner this gives a CNF,
+/// the other way round a DNF.
+fn distribute(node: ASTNode, outer: &Operator, inner: &Operator) -> ASTNode {
+    match (&node.token, node.left, node.right) {
+        (Token::Operator(op), Some(left), Some(right)) if op == outer => binary(
+            outer.clone(),
+            distribute(*left, outer, inner),
+            distribute(*right, outer, inner),
+        ),
+        (Token::Operator(op), Some(left), Some(right)) if op == inner => join(
+            distribute(*left, outer, inner),
+            distribute(*right, outer, inner),
+            outer,
+            inner,
+        ),
+        (token, left, right) => ASTNode {
+            token: token.clone(),
+            left,
+            right,
+        },
+    }
+}
+
+/// Joins two distributed trees with `inner`, pushing it below the `outer` ones of either side
+fn join(left: ASTNode, right: ASTNode, outer: &Operator, inner: &Operator) -> ASTNode {
+    let outer_token = Token::Operator(outer.clone());
+    match (left, right) {
+        (
+            ASTNode {
+                token,
+                left: Some(l_left),
+                right: Some(l_right),
+            },
+            right,
+        ) if token == outer_token => binary(
+            outer.clone(),
+            join(*l_left, right.clone(), outer, inner),
+            join(*l_right, right, outer, inner),
+        ),
+        (
+            left,
+            ASTNode {
+                token,
+                left: Some(r_left),
+                right: Some(r_right),
+            },
+        ) if token == outer_token => binary(
+            outer.clone(),
+            join(left.clone(), *r_left, outer, inner),
+            join(left, *r_right, outer, inner),
+        ),
+        (left, right) => binary(inner.clone(), left, right),
+    }
+}
+
+impl ASTNode {
+    /// Conjunctive normal form, a conjunction of clauses being disjunctions of literals.
+    /// The formula is put in NNF and then `v` is distributed over `^`,
+    /// which may grow the formula exponentially.
+    pub fn to_cnf(self) -> Result<ASTNode> {
+        Ok(distribute(self.to_nnf()?, &Operator::And, &Operator::Or))
+    }
+
+    /// Clauses of the CNF of the formula, with the tautological ones left out.
+    /// Constants don't become literals: a formula that is always true has no clauses
+    /// and one that is always false has an empty clause. Fails for a tree that
+    /// is no formula, like one with a parenthesis left as an operator.
+    pub fn cnf_clauses(&self) -> Result<Vec<Vec<Literal>>> {
+        Ok(extract_clauses(&self.clone().to_cnf()?)?.clauses)
+    }
+
+    /// Disjunctive normal form, a disjunction of terms being conjunctions of literals.
+    /// The formula is put in NNF and then `^` is distributed over `v`,
+    /// which may grow the formula exponentially.
+    pub fn to_dnf(self) -> Result<ASTNode> {
+        Ok(distribute(self.to_nnf()?, &Operator::Or, &Operator::And))
+    }
+
+    /// Terms of the DNF of the formula, with the contradictory ones left out.
+    /// A formula that is always false has no terms and one that is always true has an empty term.
+    /// Fails for a tree that is no formula, like `cnf_clauses`.
+    pub fn dnf_terms(&self) -> Result<Vec<Vec<Literal>>> {
+        let dnf = self.clone().to_dnf()?;
+        let mut terms = vec![];
+        collect_chain(&dnf, &Operator::Or, &mut terms);
+        terms
+            .into_iter()
+            .filter_map(|term| chain_literals(term, &Operator::And).transpose())
+            .collect()
+    }
+
+    /// Rewrites the formula with `^`, `v` and `~` alone, keeping the negations where they are:
+    /// `a => b` becomes `~a v b` and `a <=> b` becomes `(~a v b) ^ (~b v a)`.
+    /// Likewise `a + b` becomes `(a ^ ~b) v (~a ^ b)`, and `nand`, `nor` the negated `^`, `v`.
+    pub fn desugar(self) -> ASTNode {
+        let not = |node: ASTNode| node.make_new_root_left(Token::Operator(Operator::Not));
+        let left = self.left.map(|node| node.desugar());
+        let right = self.right.map(|node| node.desugar());
+        let (a, b) = match (&self.token, left, right) {
+            (Token::Operator(op), Some(a), Some(b)) if *op != Operator::Not => (a, b),
+            (token, left, right) => {
+                return ASTNode {
+                    token: token.clone(),
+                    left: left.map(Box::new),
+                    right: right.map(Box::new),
+                }
+            }
+        };
+        match &self.token {
+            Token::Operator(Operator::Implication) => binary(Operator::Or, not(a), b),
+            Token::Operator(Operator::Equivalence) => binary(
+                Operator::And,
+                binary(Operator::Or, not(a.clone()), b.clone()),
+                binary(Operator::Or, not(b), a),
+            ),
+            Token::Operator(Operator::Xor) => binary(
+                Operator::Or,
+                binary(Operator::And, a.clone(), not(b.clone())),
+                binary(Operator::And, not(a), b),
+            ),
+            Token::Operator(Operator::Nand) => not(binary(Operator::And, a, b)),
+            Token::Operator(Operator::Nor) => not(binary(Operator::Or, a, b)),
+            token => ASTNode {
+                token: token.clone(),
+                left: Some(Box::new(a)),
+                right: Some(Box::new(b)),
+            },
+        }
+    }
+
+    /// Negation normal form: implications, equivalences and the other connectives are
+    /// rewritten with `^`, `v` and `~`, then negations are pushed down to the variables
+    /// with De Morgan's laws, removing double negations on the way.
+    /// Fails for an operator missing an operand and for trees deeper than `MAX_DEPTH`.
+    pub fn to_nnf(self) -> Result<ASTNode> {
+        self.to_nnf_with(&EvalOptions::default())
+    }
+
+    /// Same as `to_nnf`, with trees deeper than the `max_depth` of the options rejected
+    pub fn to_nnf_with(self, options: &EvalOptions) -> Result<ASTNode> {
+        nnf(&self, false, options.max_depth)
+    }
+}
+
+/// Clauses of a CNF, each being a disjunction of its literals.
+/// No clauses at all stand for true and an empty clause for false.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Clauses {
+    pub clauses: Vec<Vec<Literal>>,
+    /// How many clauses were dropped for always being true
+    pub dropped_tautologies: usize,
+}
+
+/// Part of a clause: a literal or a constant
+enum ClauseItem {
+    Literal(Literal),
+    Constant(bool),
+}
+
+fn clause_item(node: &ASTNode) -> Result<ClauseItem> {
+    match (&node.token, &node.left) {
+        (Token::Value(Value::Bool(value)), _) => Ok(ClauseItem::Constant(*value)),
+        (Token::Value(Value::Variable(var)), _) => Ok(ClauseItem::Literal(Literal {
+            var: var.clone(),
+            negated: false,
+        })),
+        (Token::Operator(Operator::Not), Some(inner)) => match clause_item(inner)? {
+            ClauseItem::Constant(value) => Ok(ClauseItem::Constant(!value)),
+            ClauseItem::Literal(literal) if !literal.negated => {
+                Ok(ClauseItem::Literal(literal.complement()))
+            }
+            ClauseItem::Literal(_) => {
+                Err(anyhow!("Expected a literal, got {}", node.to_expr_string()))
+            }
+        },
+        _ => Err(anyhow!("Expected a literal, got {}", node.to_expr_string())),
+    }
+}
+
+/// Operands of a chain of the operator, e.g. the disjuncts of `p v (q v r)`
+fn collect_chain<'a>(node: &'a ASTNode, op: &Operator, operands: &mut Vec<&'a ASTNode>) {
+    match (&node.token, &node.left, &node.right) {
+        (Token::Operator(node_op), Some(left), Some(right)) if node_op == op => {
+            collect_chain(left, op, operands);
+            collect_chain(right, op, operands)
+        }
+        _ => operands.push(node),
+    }
+}
+
+/// Literals of a clause (a chain of `v`) or a term (a chain of `^`) with duplicates and
+/// neutral constants left out, or `None` when the chain is decided by the constant
+/// absorbing the operator or by a literal and its complement, like `p v ~p v q` is always true.
+fn chain_literals(chain: &ASTNode, op: &Operator) -> Result<Option<Vec<Literal>>> {
+    let absorbing = *op == Operator::Or;
+    let mut operands = vec![];
+    collect_chain(chain, op, &mut operands);
+    let mut literals: Vec<Literal> = vec![];
+    for operand in operands {
+        match clause_item(operand)? {
+            ClauseItem::Constant(value) if value == absorbing => return Ok(None),
+            ClauseItem::Constant(_) => continue,
+            ClauseItem::Literal(literal) => {
+                if literals.contains(&literal.complement()) {
+                    return Ok(None);
+                }
+                if !literals.contains(&literal) {
+                    literals.push(literal);
+                }
+            }
+        }
+    }
+    Ok(Some(literals))
+}
+
+/// Reads the clauses off a formula already in CNF, a conjunction of disjunctions of literals.
+/// Tautological clauses are dropped and counted, as they constrain nothing.
+pub fn extract_clauses(cnf: &ASTNode) -> Result<Clauses> {
+    let mut result = Clauses::default();
+    for clause in cnf.conjuncts() {
+        match chain_literals(clause, &Operator::Or)? {
+            Some(literals) => result.clauses.push(literals),
+            None => result.dropped_tautologies += 1,
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{entails, EvalOptions};
+    use crate::lexer::{Operator, Token, Value};
+    use crate::parser::{parse, ASTNode};
+    use crate::table::truth_table;
+
+    use super::{extract_clauses, Literal};
+
+    fn formula(clauses: &[Vec<Literal>]) -> ASTNode {
+        let clauses: Vec<String> = clauses
+            .iter()
+            .map(|clause| {
+                let literals: Vec<String> = clause.iter().map(|l| l.to_string()).collect();
+                format!("({} v 0)", literals.join(" v "))
+            })
+            .collect();
+        parse(&format!("{} ^ 1", clauses.join(" ^ ")))
+    }
+
+    /// Negations only in front of variables and no connectives besides `^` and `v`
+    fn is_nnf(node: &ASTNode) -> bool {
+        match &node.token {
+            Token::Operator(Operator::Not) => matches!(
+                node.left.as_deref().map(|left| &left.token),
+                Some(Token::Value(Value::Variable(_)))
+            ),
+            Token::Operator(Operator::And | Operator::Or) => {
+                node.left.as_deref().is_some_and(is_nnf)
+                    && node.right.as_deref().is_some_and(is_nnf)
+            }
+            Token::Operator(_) => false,
+            Token::Value(_) => true,
+        }
+    }
+
+    #[test]
+    fn test_to_nnf_de_morgan() {
+        assert_eq!(parse("~(p ^ q)").to_nnf().unwrap(), parse("~p v ~q"));
+        assert_eq!(parse("~(p v ~q)").to_nnf().unwrap(), parse("~p ^ q"));
+    }
+
+    #[test]
+    fn test_to_nnf_double_negation() {
+        assert_eq!(parse("~~p").to_nnf().unwrap(), parse("p"));
+        assert_eq!(parse("~~~(p => q)").to_nnf().unwrap(), parse("p ^ ~q"));
+    }
+
+    #[test]
+    fn test_to_nnf_implication() {
+        assert_eq!(parse("p => ~q").to_nnf().unwrap(), parse("~p v ~q"));
+    }
+
+    #[test]
+    fn test_to_nnf_missing_operand() {
+        let node = ASTNode {
+            token: Token::Operator(Operator::Implication),
+            left: Some(Box::new(parse("p"))),
+            right: None,
+        };
+
+        let error = node
+            .make_new_root_left(Token::Operator(Operator::Not))
+            .to_nnf();
+
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Operator 'Implication' missing operand"
+        );
+    }
+
+    #[test]
+    fn test_to_nnf_keeps_meaning() {
+        for expr in [
+            "p <=> q",
+            "~(p <=> q)",
+            "p + ~q",
+            "~(p + q) v r",
+            "p nand (q nor ~r)",
+            "~(p nand q) => (r <=> ~1)",
+        ] {
+            let node = parse(expr);
+
+            let result = node.clone().to_nnf().unwrap();
+
+            assert!(is_nnf(&result), "{} gave {}", expr, result.to_expr_string());
+            assert!(entails(&node, &result).unwrap(), "{}", expr);
+            assert!(entails(&result, &node).unwrap(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_desugar_implication_and_equivalence() {
+        assert_eq!(parse("p => q").desugar(), parse("~p v q"));
+        assert_eq!(parse("p <=> q").desugar(), parse("(~p v q) ^ (~q v p)"));
+        assert_eq!(
+            parse("~(p => q) <=> r").desugar(),
+            parse("(~~(~p v q) v r) ^ (~r v ~(~p v q))")
+        );
+    }
+
+    #[test]
+    fn test_desugar_keeps_meaning() {
+        fn is_basic(node: &ASTNode) -> bool {
+            let basic = matches!(
+                node.token,
+                Token::Value(_) | Token::Operator(Operator::And | Operator::Or | Operator::Not)
+            );
+            basic
+                && node.left.as_deref().is_none_or(is_basic)
+                && node.right.as_deref().is_none_or(is_basic)
+        }
+        for expr in [
+            "p => q => r",
+            "(p <=> ~q) ^ r",
+            "p + q <=> 1",
+            "(p nand q) nor ~(r => p)",
+        ] {
+            let node = parse(expr);
+
+            let result = node.clone().desugar();
+
+            assert!(
+                is_basic(&result),
+                "{} gave {}",
+                expr,
+                result.to_expr_string()
+            );
+            assert_eq!(
+                truth_table(&result).unwrap(),
+                truth_table(&node).unwrap(),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_cnf_distributes_disjunction() {
+        assert_eq!(
+            parse("(p ^ q) v r").to_cnf().unwrap(),
+            parse("(p v r) ^ (q v r)")
+        );
+        assert_eq!(
+            parse("(p ^ q) v (r ^ s)").to_cnf().unwrap(),
+            parse("((p v r) ^ (p v s)) ^ ((q v r) ^ (q v s))")
+        );
+    }
+
+    #[test]
+    fn test_to_cnf_keeps_meaning() {
+        for expr in ["p <=> (q ^ r)", "~((p => q) v ~r)", "(p + q) v (r nand p)"] {
+            let node = parse(expr);
+
+            let result = node.clone().to_cnf().unwrap();
+
+            assert!(
+                extract_clauses(&result).is_ok(),
+                "{}",
+                result.to_expr_string()
+            );
+            assert!(entails(&node, &result).unwrap(), "{}", expr);
+            assert!(entails(&result, &node).unwrap(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_cnf_clauses() {
+        let clauses: Vec<Vec<String>> = parse("(p ^ ~q) v r")
+            .cnf_clauses()
+            .unwrap()
+            .iter()
+            .map(|clause| clause.iter().map(|l| l.to_string()).collect())
+            .collect();
+
+        assert_eq!(clauses, vec![vec!["p", "r"], vec!["~q", "r"]]);
+    }
+
+    #[test]
+    fn test_cnf_clauses_of_constants() {
+        assert!(parse("p v ~p").cnf_clauses().unwrap().is_empty());
+        assert_eq!(
+            parse("p ^ 0").cnf_clauses().unwrap(),
+            vec![
+                vec![Literal {
+                    var: "p".to_string(),
+                    negated: false
+                }],
+                vec![]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cnf_clauses_of_malformed_tree() {
+        let node = parse("p").make_new_root_left(Token::Operator(Operator::ParenthisOpen));
+
+        assert!(node.cnf_clauses().is_err());
+    }
+
+    #[test]
+    fn test_to_dnf_distributes_conjunction() {
+        assert_eq!(
+            parse("(p v q) ^ r").to_dnf().unwrap(),
+            parse("(p ^ r) v (q ^ r)")
+        );
+        assert_eq!(parse("~p").to_dnf().unwrap(), parse("~p"));
+        assert_eq!(parse("p").to_dnf().unwrap(), parse("p"));
+    }
+
+    #[test]
+    fn test_to_dnf_keeps_meaning() {
+        for expr in ["p <=> (q v r)", "~((p => q) ^ ~r)", "(p + q) ^ (r nor p)"] {
+            let node = parse(expr);
+
+            let result = node.clone().to_dnf().unwrap();
+
+            assert!(entails(&node, &result).unwrap(), "{}", expr);
+            assert!(entails(&result, &node).unwrap(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_dnf_terms() {
+        let terms: Vec<Vec<String>> = parse("(p v ~q) ^ r ^ (q v 0)")
+            .dnf_terms()
+            .unwrap()
+            .iter()
+            .map(|term| term.iter().map(|l| l.to_string()).collect())
+            .collect();
+
+        assert_eq!(terms, vec![vec!["p", "r", "q"]]);
+        assert!(parse("p ^ ~p").dnf_terms().unwrap().is_empty());
+        assert_eq!(parse("1 ^ ~0").dnf_terms().unwrap(), vec![vec![]]);
+        let node = parse("p").make_new_root_left(Token::Operator(Operator::ParenthisOpen));
+        assert!(node.dnf_terms().is_err());
+    }
+
+    #[test]
+    fn test_extract_clauses() {
+        let result = extract_clauses(&parse("(p v ~q) ^ r ^ (~r v q v q)")).unwrap();
+
+        let clauses: Vec<Vec<String>> = result
+            .clauses
+            .iter()
+            .map(|clause| clause.iter().map(|l| l.to_string()).collect())
+            .collect();
+        assert_eq!(clauses, vec![vec!["p", "~q"], vec!["r"], vec!["~r", "q"]]);
+        assert_eq!(result.dropped_tautologies, 0);
+    }
+
+    #[test]
+    fn test_extract_clauses_drops_tautological_clauses() {
+        let cnf = parse("(p v ~p v q) ^ (q v r) ^ (~r v 1) ^ (~q v r v q)");
+
+        let result = extract_clauses(&cnf).unwrap();
+
+        assert_eq!(result.clauses.len(), 1);
+        assert_eq!(result.dropped_tautologies, 3);
+        let remaining = formula(&result.clauses);
+        assert!(entails(&cnf, &remaining).unwrap());
+        assert!(entails(&remaining, &cnf).unwrap());
+    }
+
+    #[test]
+    fn test_extract_clauses_should_fail_outside_cnf() {
+        assert!(extract_clauses(&parse("(p ^ q) v r")).is_err());
+        assert!(extract_clauses(&parse("~~p")).is_err());
+    }
+
+    #[test]
+    fn test_to_nnf_depth_limit() {
+        let within = |max_depth| parse("~(p => ~q)").to_nnf_with(&EvalOptions { max_depth });
+
+        assert_eq!(
+            within(3).unwrap_err().to_string(),
+            "Expression too deeply nested"
+        );
+        assert_eq!(within(4).unwrap(), parse("p ^ q"));
+    }
+}
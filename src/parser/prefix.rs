@@ -0,0 +1,119 @@
+use anyhow::{anyhow, Result};
+
+use crate::lexer::{Lexer, Operator, Token};
+
+use super::{ASTNode, ParseOptions};
+
+/// Parses a formula in prefix (Polish) notation, e.g. `^ 1 v 0 1`, the inverse of `to_prefix`.
+/// Every operator is followed by its operands, so no parentheses are needed.
+/// Assignments aren't supported and all the variables are left free.
+pub fn construct_ast_prefix(lexer: &mut Lexer) -> Result<ASTNode> {
+    construct_ast_prefix_with(lexer, &ParseOptions::default())
+}
+
+/// Same as `construct_ast_prefix`, with trees deeper than the `max_depth` of the options rejected.
+/// The other options don't apply to the prefix notation.
+pub fn construct_ast_prefix_with(lexer: &mut Lexer, options: &ParseOptions) -> Result<ASTNode> {
+    let root = prefix_operand(lexer, options.max_depth)?;
+    match lexer.next() {
+        Some(token) => {
+            let token = token?;
+            Err(anyhow!(
+                "Unexpected {:?} at {} after the end of the formula",
+                token.value,
+                token.span
+            ))
+        }
+        None => Ok(root),
+    }
+}
+
+/// Operand at most `depth` deep
+fn prefix_operand(lexer: &mut Lexer, depth: usize) -> Result<ASTNode> {
+    let token = match lexer.next() {
+        Some(token) => token?,
+        None => {
+            return Err(anyhow!(
+                "Invalid syntax: formula ends before all operands are given"
+            ))
+        }
+    };
+    if depth == 0 {
+        return Err(anyhow!("Expression too deeply nested at {}", token.span));
+    }
+    match token.value {
+        Token::Value(value) => Ok(ASTNode::new(Token::Value(value))),
+        Token::Operator(Operator::Not) => Ok(
+            prefix_operand(lexer, depth - 1)?.make_new_root_left(Token::Operator(Operator::Not))
+        ),
+        Token::Operator(op) if op.is_binary() => {
+            let left = prefix_operand(lexer, depth - 1)?;
+            let right = prefix_operand(lexer, depth - 1)?;
+            Ok(ASTNode {
+                token: Token::Operator(op),
+                left: Some(Box::new(left)),
+                right: Some(Box::new(right)),
+            })
+        }
+        Token::Operator(op) => Err(anyhow!(
+            "Unexpected '{}' at {}, prefix notation takes only operators and operands",
+            op.symbol(),
+            token.span
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::lexer::Lexer;
+    use crate::parser::{parse, ParseOptions};
+
+    use super::{construct_ast_prefix, construct_ast_prefix_with};
+
+    #[rstest]
+    #[case("^ 1 v 0 1", "1 ^ (0 v 1)")]
+    #[case("=> => p q r", "(p => q) => r")]
+    #[case("nand ~ <=> p ~ q + r 1", "~(p <=> ~q) nand (r + 1)")]
+    fn test_construct_ast_prefix(#[case] prefix: &str, #[case] infix: &str) {
+        let root = construct_ast_prefix(&mut Lexer::new(prefix)).unwrap();
+
+        assert_eq!(root, parse(infix));
+        assert_eq!(root.to_prefix(), prefix);
+    }
+
+    #[rstest]
+    #[case("^ 1", "Invalid syntax: formula ends before all operands are given")]
+    #[case(
+        "v 1 0 1",
+        "Unexpected Value(Bool(true)) at 1:7 after the end of the formula"
+    )]
+    #[case(
+        "^ (1) 0",
+        "Unexpected '(' at 1:3, prefix notation takes only operators and operands"
+    )]
+    fn test_construct_ast_prefix_should_fail(#[case] prefix: &str, #[case] expected: &str) {
+        let result = construct_ast_prefix(&mut Lexer::new(prefix));
+
+        assert_eq!(result.unwrap_err().to_string(), expected);
+    }
+
+    #[test]
+    fn test_construct_ast_prefix_depth_limit() {
+        let negations = format!("{}1", "~ ".repeat(10_000));
+        let shallow = ParseOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        let result = construct_ast_prefix(&mut Lexer::new(&negations));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Expression too deeply nested at 1:2001"
+        );
+        assert!(construct_ast_prefix_with(&mut Lexer::new("v 1 0"), &shallow).is_ok());
+        assert!(construct_ast_prefix_with(&mut Lexer::new("v ~ 1 0"), &shallow).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::Result;
+
+use crate::lexer::{Lexer, Operator, Spanned, Token};
+
+use super::construct::construct;
+use super::{ASTNode, ParseOptions, StoredVariables};
+
+/// State of the shunting yard right after handling a token
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseStep {
+    pub token: Token,
+    pub operators: Vec<Operator>,
+    /// Trees waiting for their parent operator, in infix notation
+    pub tree_queue: Vec<String>,
+}
+
+/// Record of a parsing session, meant to be attached to parser bug reports
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseTrace {
+    pub input: String,
+    pub options: ParseOptions,
+    /// Every token read from the lexer, including the ones only peeked at
+    pub tokens: Vec<Spanned<Token>>,
+    pub steps: Vec<ParseStep>,
+}
+
+/// Parses the input like `construct_ast_with`, recording the session on the way.
+/// The trace is returned also when parsing fails, as that's when it's needed the most.
+pub fn construct_ast_traced(
+    input: &str,
+    options: &ParseOptions,
+) -> (ParseTrace, Result<(ASTNode, StoredVariables)>) {
+    let mut tokens = vec![];
+    let mut steps = vec![];
+    let lexer = Lexer::new(input).inspect(|token| {
+        if let Ok(token) = token {
+            tokens.push(token.clone());
+        }
+    });
+    let result = construct(lexer, options, &mut |token, operators, tree_queue| {
+        steps.push(ParseStep {
+            token: token.clone(),
+            operators: operators.to_vec(),
+            tree_queue: tree_queue
+                .iter()
+                .map(|tree| tree.to_expr_string())
+                .collect(),
+        })
+    });
+
+    let trace = ParseTrace {
+        input: input.to_string(),
+        options: options.clone(),
+        tokens,
+        steps,
+    };
+    (trace, result)
+}
+
+/// Builds the AST again out of the recorded tokens, skipping the lexer
+pub fn replay(trace: &ParseTrace) -> Result<ASTNode> {
+    let tokens = trace.tokens.iter().cloned().map(Ok);
+    let (root, _) = construct(tokens, &trace.options, &mut |_, _, _| {})?;
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::{Operator, Token, Value};
+    use crate::parser::ParseOptions;
+
+    use super::{construct_ast_traced, replay};
+
+    #[test]
+    fn test_trace_records_steps() {
+        let (trace, result) = construct_ast_traced("p := 1 p ^ ~0", &ParseOptions::default());
+
+        let (root, _) = result.unwrap();
+        assert_eq!(trace.tokens.len(), 7);
+        assert_eq!(trace.steps.len(), 7);
+        let last = trace.steps.last().unwrap();
+        assert_eq!(last.token, Token::Value(Value::Bool(false)));
+        assert_eq!(last.operators, vec![Operator::And, Operator::Not]);
+        assert_eq!(last.tree_queue, vec!["p", "0"]);
+        assert_eq!(replay(&trace).unwrap(), root);
+    }
+
+    #[test]
+    fn test_trace_is_kept_on_failure() {
+        let (trace, result) = construct_ast_traced("p ^ q", &ParseOptions::default());
+
+        assert!(result.is_err());
+        assert_eq!(
+            trace.tokens[0].value,
+            Token::Value(Value::Variable("p".to_string()))
+        );
+        // Undefined variables are reported once the whole statement was parsed
+        assert_eq!(trace.steps.len(), 3);
+        assert!(replay(&trace).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_trace_serialized_replays_to_same_ast() {
+        let options = ParseOptions {
+            free_variables: true,
+            ..Default::default()
+        };
+        let (trace, result) = construct_ast_traced("~(p v q) => r <=> p + 1", &options);
+        let (root, _) = result.unwrap();
+
+        let json = serde_json::to_string(&trace).unwrap();
+        let restored: super::ParseTrace = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored, trace);
+        assert_eq!(replay(&restored).unwrap(), root);
+    }
+}
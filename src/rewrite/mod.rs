@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::lexer::{Token, Value};
+use crate::parser::ASTNode;
+
+/// Direction taken when descending from a node to one of its children
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Left,
+    Right,
+}
+
+/// Location of a subtree, as steps from the root
+pub type Path = Vec<Step>;
+
+/// Subtrees bound to the metavariables of a pattern
+pub type Bindings = HashMap<String, ASTNode>;
+
+/// Upper bound on rewrites done by `apply_rules`, as the rules may never reach a fixpoint
+pub const MAX_REWRITES: usize = 1000;
+
+/// Limits of the rewriting
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewriteOptions {
+    /// Most rewrites done before giving up on a fixpoint, `MAX_REWRITES` unless changed
+    pub max_rewrites: usize,
+}
+
+impl Default for RewriteOptions {
+    fn default() -> Self {
+        RewriteOptions {
+            max_rewrites: MAX_REWRITES,
+        }
+    }
+}
+
+/// Rewrites subtrees matching `lhs` into `rhs`, with the metavariables bound by the match
+#[derive(Debug, Clone)]
+pub struct RewriteRule {
+    pub lhs: ASTNode,
+    pub rhs: ASTNode,
+}
+
+/// Pattern leaves named `?x` match any subtree
+fn metavariable(node: &ASTNode) -> Option<&str> {
+    match &node.token {
+        Token::Value(Value::Variable(name)) if name.starts_with('?') => Some(name),
+        _ => None,
+    }
+}
+
+/// Matches the pattern against the root of the target, extending the bindings.
+/// A metavariable that occurs many times has to match structurally equal subtrees.
+fn match_node(pattern: &ASTNode, target: &ASTNode, bindings: &mut Bindings) -> bool {
+    if let Some(name) = metavariable(pattern) {
+        return match bindings.get(name) {
+            Some(bound) => bound == target,
+            None => {
+                bindings.insert(name.to_string(), target.clone());
+                true
+            }
+        };
+    }
+    pattern.token == target.token
+        && match_child(&pattern.left, &target.left, bindings)
+        && match_child(&pattern.right, &target.right, bindings)
+}
+
+fn match_child(
+    pattern: &Option<Box<ASTNode>>,
+    target: &Option<Box<ASTNode>>,
+    bindings: &mut Bindings,
+) -> bool {
+    match (pattern, target) {
+        (Some(pattern), Some(target)) => match_node(pattern, target, bindings),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn collect_matches(
+    pattern: &ASTNode,
+    target: &ASTNode,
+    path: &mut Path,
+    matches: &mut Vec<(Path, Bindings)>,
+) {
+    let mut bindings = Bindings::new();
+    if match_node(pattern, target, &mut bindings) {
+        matches.push((path.clone(), bindings));
+    }
+    for (step, child) in [(Step::Left, &target.left), (Step::Right, &target.right)] {
+        if let Some(child) = child {
+            path.push(step);
+            collect_matches(pattern, child, path, matches);
+            path.pop();
+        }
+    }
+}
+
+/// All the subtrees of the target matched by the pattern, in pre-order
+pub fn find_matches(pattern: &ASTNode, target: &ASTNode) -> Vec<(Path, Bindings)> {
+    let mut matches = vec![];
+    collect_matches(pattern, target, &mut vec![], &mut matches);
+    matches
+}
+
+/// Builds the template with its metavariables replaced by the bound subtrees
+fn instantiate(template: &ASTNode, bindings: &Bindings) -> ASTNode {
+    if let Some(bound) = metavariable(template).and_then(|name| bindings.get(name)) {
+        return bound.clone();
+    }
+    ASTNode {
+        token: template.token.clone(),
+        left: template
+            .left
+            .as_ref()
+            .map(|left| Box::new(instantiate(left, bindings))),
+        right: template
+            .right
+            .as_ref()
+            .map(|right| Box::new(instantiate(right, bindings))),
+    }
+}
+
+fn replace_at(node: &mut ASTNode, path: &[Step], replacement: ASTNode) {
+    match path.split_first() {
+        None => *node = replacement,
+        Some((step, rest)) => {
+            let child = match step {
+                Step::Left => node.left.as_mut(),
+                Step::Right => node.right.as_mut(),
+            };
+            if let Some(child) = child {
+                replace_at(child, rest, replacement);
+            }
+        }
+    }
+}
+
+/// Rewrites the first match of the first applicable rule, over and over,
+/// until none of the rules matches anymore. Fails when the rules still match
+/// after `MAX_REWRITES` rewrites, as they may never reach a fixpoint.
+pub fn apply_rules(node: ASTNode, rules: &[RewriteRule]) -> Result<ASTNode> {
+    apply_rules_with(node, rules, &RewriteOptions::default())
+}
+
+/// Same as `apply_rules` within the given limits
+pub fn apply_rules_with(
+    node: ASTNode,
+    rules: &[RewriteRule],
+    options: &RewriteOptions,
+) -> Result<ASTNode> {
+    let mut node = node;
+    for rewrites in 0..=options.max_rewrites {
+        let rewrite = rules.iter().find_map(|rule| {
+            find_matches(&rule.lhs, &node)
+                .into_iter()
+                .next()
+                .map(|(path, bindings)| (path, instantiate(&rule.rhs, &bindings)))
+        });
+        match rewrite {
+            Some(_) if rewrites == options.max_rewrites => break,
+            Some((path, replacement)) => replace_at(&mut node, &path, replacement),
+            None => return Ok(node),
+        }
+    }
+    Err(anyhow!(
+        "No fixpoint after {} rewrites",
+        options.max_rewrites
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+
+    use super::{apply_rules, apply_rules_with, find_matches, RewriteOptions, RewriteRule, Step};
+
+    #[test]
+    fn test_find_matches_binds_consistently() {
+        let pattern = parse("?x ^ ?x");
+        let target = parse("(p ^ p) v (q ^ r)");
+
+        let matches = find_matches(&pattern, &target);
+
+        assert_eq!(matches.len(), 1);
+        let (path, bindings) = &matches[0];
+        assert_eq!(path, &vec![Step::Left]);
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings["?x"], parse("p"));
+    }
+
+    #[test]
+    fn test_find_matches_binds_subtrees() {
+        let pattern = parse("?x v ?y");
+        let target = parse("~(p ^ q) v r");
+
+        let matches = find_matches(&pattern, &target);
+
+        assert_eq!(matches.len(), 1);
+        let (path, bindings) = &matches[0];
+        assert!(path.is_empty());
+        assert_eq!(bindings["?x"], parse("~(p ^ q)"));
+        assert_eq!(bindings["?y"], parse("r"));
+    }
+
+    #[test]
+    fn test_find_matches_nested_occurrences() {
+        let pattern = parse("~?x");
+        let target = parse("~(p ^ ~q)");
+
+        let paths: Vec<_> = find_matches(&pattern, &target)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+
+        assert_eq!(paths, vec![vec![], vec![Step::Left, Step::Right]]);
+    }
+
+    #[test]
+    fn test_find_matches_concrete_leaves_must_be_equal() {
+        let pattern = parse("p ^ ?x");
+        let target = parse("(q ^ 1) v (p ^ 0)");
+
+        let matches = find_matches(&pattern, &target);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0, vec![Step::Right]);
+    }
+
+    fn rule(lhs: &str, rhs: &str) -> RewriteRule {
+        RewriteRule {
+            lhs: parse(lhs),
+            rhs: parse(rhs),
+        }
+    }
+
+    #[test]
+    fn test_apply_rules_double_negation() {
+        let rules = vec![rule("~~?x", "?x")];
+
+        let result = apply_rules(parse("~~~~p"), &rules).unwrap();
+
+        assert_eq!(result, parse("p"));
+    }
+
+    #[test]
+    fn test_apply_rules_rewrites_nested_subtrees() {
+        let rules = vec![rule("?x => ?y", "~?x v ?y"), rule("~~?x", "?x")];
+
+        let result = apply_rules(parse("(~p => q) ^ (q => r)"), &rules).unwrap();
+
+        assert_eq!(result, parse("(p v q) ^ (~q v r)"));
+    }
+
+    #[test]
+    fn test_apply_rules_stops_without_fixpoint() {
+        let rules = vec![rule("?x ^ ?y", "?y ^ ?x")];
+
+        let result = apply_rules(parse("p ^ q"), &rules);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No fixpoint after 1000 rewrites"
+        );
+    }
+
+    #[test]
+    fn test_apply_rules_configured_limit() {
+        let rules = vec![rule("~~?x", "?x")];
+        let options = RewriteOptions { max_rewrites: 1 };
+
+        let result = apply_rules_with(parse("~~~~p"), &rules, &options);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No fixpoint after 1 rewrites"
+        );
+        let options = RewriteOptions { max_rewrites: 2 };
+        let result = apply_rules_with(parse("~~~~p"), &rules, &options).unwrap();
+        assert_eq!(result, parse("p"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::lexer::{Operator, Token, Value};
+use crate::parser::{ASTNode, FlatNode};
+
+impl ASTNode {
+    /// Equivalent formula with the constants folded away, double negations removed
+    /// and the idempotence, complementation and absorption laws applied,
+    /// repeated until none of them changes anything. Operands are compared up to
+    /// the order of commutative operands, so `p ^ q` and `q ^ p` count as the same.
+    /// Fails for an operator missing an operand.
+    pub fn simplify(self) -> Result<ASTNode> {
+        let mut node = self;
+        loop {
+            let folded = remove_double_negation(node.clone().fold_constants());
+            let next = apply_laws(folded.flatten_associative()?).to_ast();
+            if next == node {
+                return Ok(next);
+            }
+            node = next;
+        }
+    }
+}
+
+fn apply_laws(node: FlatNode) -> FlatNode {
+    match node {
+        FlatNode::Value(_) => node,
+        FlatNode::Not(operand) => FlatNode::Not(Box::new(apply_laws(*operand))),
+        FlatNode::Binary(op, left, right) => FlatNode::Binary(
+            op,
+            Box::new(apply_laws(*left)),
+            Box::new(apply_laws(*right)),
+        ),
+        FlatNode::Chain(op, operands) => {
+            reduce_chain(op, operands.into_iter().map(apply_laws).collect())
+        }
+    }
+}
+
+/// Key under which equivalent operands meet, up to the order of commutative operands:
+/// the same operand with the operands of chains and other commutative operators sorted
+fn key(node: &FlatNode) -> FlatNode {
+    match node {
+        FlatNode::Value(_) => node.clone(),
+        FlatNode::Not(operand) => FlatNode::Not(Box::new(key(operand))),
+        FlatNode::Chain(op, operands) => {
+            let mut keys: Vec<FlatNode> = operands.iter().map(key).collect();
+            keys.sort();
+            FlatNode::Chain(op.clone(), keys)
+        }
+        FlatNode::Binary(op, left, right) => {
+            let (mut left, mut right) = (key(left), key(right));
+            if op.is_commutative() && left > right {
+                std::mem::swap(&mut left, &mut right);
+            }
+            FlatNode::Binary(op.clone(), Box::new(left), Box::new(right))
+        }
+    }
+}
+
+fn reduce_chain(op: Operator, operands: Vec<FlatNode>) -> FlatNode {
+    // Simplified operands may have become chains of the same operator
+    let mut chain = vec![];
+    for operand in operands {
+        match operand {
+            FlatNode::Chain(inner, inner_operands) if inner == op => chain.extend(inner_operands),
+            other => chain.push(other),
+        }
+    }
+    let keys: Vec<FlatNode> = chain.iter().map(key).collect();
+    // Idempotence: p ^ p is p
+    let mut seen = HashSet::new();
+    let mut kept: Vec<(FlatNode, &FlatNode)> = chain
+        .into_iter()
+        .zip(&keys)
+        .filter(|(_, key)| seen.insert(*key))
+        .collect();
+    // Complementation: p ^ ~p is 0 and p v ~p is 1
+    let complemented = kept
+        .iter()
+        .any(|(_, key)| matches!(key, FlatNode::Not(inner) if seen.contains(inner.as_ref())));
+    if complemented {
+        return FlatNode::Value(Value::Bool(op == Operator::Or));
+    }
+    // Absorption: p v (p ^ q) is p and p ^ (p v q) is p
+    let dual = if op == Operator::And {
+        Operator::Or
+    } else {
+        Operator::And
+    };
+    kept.retain(|(_, key)| match key {
+        FlatNode::Chain(inner, items) if *inner == dual => {
+            !items.iter().any(|item| seen.contains(item))
+        }
+        _ => true,
+    });
+    FlatNode::Chain(op, kept.into_iter().map(|(operand, _)| operand).collect())
+}
+
+fn remove_double_negation(node: ASTNode) -> ASTNode {
+    match (&node.token, node.left) {
+        (Token::Operator(Operator::Not), Some(inner))
+            if inner.token == Token::Operator(Operator::Not) =>
+        {
+            match inner.left {
+                Some(operand) => remove_double_negation(*operand),
+                None => ASTNode {
+                    token: Token::Operator(Operator::Not),
+                    left: Some(inner),
+                    right: None,
+                },
+            }
+        }
+        (_, left) => ASTNode {
+            token: node.token,
+            left: left.map(|left| Box::new(remove_double_negation(*left))),
+            right: node
+                .right
+                .map(|right| Box::new(remove_double_negation(*right))),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::lexer::{Operator, Token};
+    use crate::parser::{parse, ASTNode};
+    use crate::table::truth_table_over;
+
+    #[test]
+    fn test_simplify_folds_constants() {
+        let result = parse("(p ^ 1) v (q ^ 0)").simplify().unwrap();
+
+        assert_eq!(result, parse("p"));
+    }
+
+    #[test]
+    fn test_simplify_removes_double_negation() {
+        let result = parse("~~~p v ~~(q ^ ~~r)").simplify().unwrap();
+
+        assert_eq!(result, parse("~p v (q ^ r)"));
+    }
+
+    #[test]
+    fn test_simplify_fails_on_missing_operand() {
+        let mut node = parse("p ^ (q v r)");
+        node.right = Some(Box::new(ASTNode::new(Token::Operator(Operator::Or))));
+
+        assert!(node.simplify().is_err());
+    }
+
+    #[rstest]
+    #[case("p ^ p", "p")]
+    #[case("(p v q) ^ (q v p)", "p v q")]
+    #[case("p ^ q ^ ~p", "0")]
+    #[case("~(r => p) v q v (r => p)", "1")]
+    #[case("p v (p ^ q)", "p")]
+    #[case("(q v p) ^ r ^ p", "r ^ p")]
+    #[case("~~(p ^ ~~p)", "p")]
+    #[case("(p ^ 1) ^ (p v ~p v q) ^ ~q", "p ^ ~q")]
+    #[case("(p + (q ^ r)) v ((r ^ q) + p)", "p + (q ^ r)")]
+    #[case("(p ^ q ^ r) v ~(r ^ (q ^ p))", "1")]
+    fn test_simplify_laws(#[case] expr: &str, #[case] expected: &str) {
+        let node = parse(expr);
+        let vars: Vec<String> = node.free_variables().into_iter().collect();
+
+        let result = node.clone().simplify().unwrap();
+
+        assert_eq!(result, parse(expected), "{}", result.to_expr_string());
+        assert_eq!(
+            truth_table_over(&result, &vars).unwrap(),
+            truth_table_over(&node, &vars).unwrap()
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::interpreter::{assignments, evaluate_ref};
+use crate::parser::{ASTNode, Literal, StoredVariables};
+
+/// Largest number of variables `all_models` enumerates the assignments of
+pub const MAX_MODEL_VARIABLES: usize = 20;
+
+type Clauses = Vec<Vec<Literal>>;
+
+/// Clauses left after making the literal true: the clauses it satisfies
+/// are dropped and its complement is removed from the rest
+fn assume(clauses: &[Vec<Literal>], literal: &Literal) -> Clauses {
+    let complement = literal.complement();
+    clauses
+        .iter()
+        .filter(|clause| !clause.contains(literal))
+        .map(|clause| {
+            clause
+                .iter()
+                .filter(|l| **l != complement)
+                .cloned()
+                .collect()
+        })
+        .collect()
+}
+
+fn dpll(mut clauses: Clauses, mut model: HashMap<String, bool>) -> Option<HashMap<String, bool>> {
+    // Unit propagation: a clause of a single literal leaves no choice
+    while let Some(unit) = clauses
+        .iter()
+        .find(|clause| clause.len() == 1)
+        .map(|clause| clause[0].clone())
+    {
+        model.insert(unit.var.clone(), !unit.negated);
+        clauses = assume(&clauses, &unit);
+    }
+    if clauses.iter().any(|clause| clause.is_empty()) {
+        return None;
+    }
+    // Splitting on a variable of the first clause, every clause has at least two literals here
+    let literal = match clauses.first() {
+        Some(clause) => clause[0].clone(),
+        None => return Some(model),
+    };
+    for chosen in [literal.clone(), literal.complement()] {
+        let mut branch = model.clone();
+        branch.insert(chosen.var.clone(), !chosen.negated);
+        if let Some(found) = dpll(assume(&clauses, &chosen), branch) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Finds an assignment of the free variables satisfying the formula with the DPLL procedure
+/// over the clauses of its CNF, or `None` when the formula is unsatisfiable.
+/// Variables that don't matter for the result are assigned false.
+/// Fails when the tree has no clauses to read, instead of reporting it unsatisfiable.
+pub fn solve_sat(node: &ASTNode) -> Result<Option<HashMap<String, bool>>> {
+    let model = dpll(node.cnf_clauses()?, HashMap::new()).map(|mut model| {
+        for var in node.free_variables() {
+            model.entry(var).or_insert(false);
+        }
+        model
+    });
+    Ok(model)
+}
+
+/// Every assignment of the free variables satisfying the formula, in truth-table order
+pub fn all_models(node: &ASTNode) -> Result<Vec<StoredVariables>> {
+    all_models_with_limit(node, MAX_MODEL_VARIABLES)
+}
+
+/// Same as `all_models`, failing when the formula has more than `max_variables` variables
+/// instead of enumerating the exponentially many assignments
+pub fn all_models_with_limit(node: &ASTNode, max_variables: usize) -> Result<Vec<StoredVariables>> {
+    let variables: Vec<String> = node.free_variables().into_iter().collect();
+    if variables.len() > max_variables {
+        return Err(anyhow!(
+            "Too many variables to enumerate models: {}, the limit is {}",
+            variables.len(),
+            max_variables
+        ));
+    }
+    let mut models = vec![];
+    for values in assignments(&variables)? {
+        let assigned: StoredVariables = variables.iter().cloned().zip(values).collect();
+        if evaluate_ref(node, &assigned)? {
+            models.push(assigned);
+        }
+    }
+    Ok(models)
+}
+
+/// Number of satisfying assignments of the free variables
+pub fn count_models(node: &ASTNode) -> Result<usize> {
+    Ok(all_models(node)?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::evaluate_ref;
+    use crate::lexer::{Operator, Token};
+    use crate::parser::parse;
+
+    use super::{all_models, all_models_with_limit, count_models, solve_sat};
+
+    #[test]
+    fn test_solve_sat_contradiction() {
+        assert_eq!(solve_sat(&parse("p ^ ~p")).unwrap(), None);
+        assert_eq!(
+            solve_sat(&parse("(p v q) ^ (~p v q) ^ (p v ~q) ^ (~p v ~q)")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_solve_sat_fails_on_malformed_tree() {
+        let node = parse("p").make_new_root_left(Token::Operator(Operator::ParenthisOpen));
+
+        assert!(solve_sat(&node).is_err());
+    }
+
+    #[test]
+    fn test_solve_sat_models_satisfy_formula() {
+        for expr in [
+            "p v q",
+            "(p => q) ^ (q => r) ^ p",
+            "(p + q) ^ (q <=> ~r) ^ (r v s)",
+            "~(p nand q) ^ (r nor ~p)",
+            "p v ~p v q",
+        ] {
+            let root = parse(expr);
+
+            let model = solve_sat(&root).unwrap().unwrap();
+
+            assert_eq!(model.len(), root.free_variables().len(), "{}", expr);
+            assert!(evaluate_ref(&root, &model).unwrap(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_count_models() {
+        assert_eq!(count_models(&parse("p v q")).unwrap(), 3);
+        assert_eq!(count_models(&parse("p <=> q")).unwrap(), 2);
+        assert_eq!(count_models(&parse("p ^ ~p")).unwrap(), 0);
+        assert_eq!(count_models(&parse("1")).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_all_models() {
+        let models = all_models(&parse("p <=> q")).unwrap();
+
+        let values: Vec<(bool, bool)> = models.iter().map(|m| (m["p"], m["q"])).collect();
+        assert_eq!(values, vec![(false, false), (true, true)]);
+    }
+
+    #[test]
+    fn test_all_models_should_fail_above_limit() {
+        let root = parse("(p v q) ^ r");
+
+        assert!(all_models_with_limit(&root, 2).is_err());
+        assert_eq!(all_models_with_limit(&root, 3).unwrap().len(), 3);
+    }
+}
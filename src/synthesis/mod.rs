@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+
+use crate::lexer::{Operator, Token, Value};
+use crate::minimize::{join, Implicant};
+use crate::parser::ASTNode;
+
+/// Formula that is true exactly for the given rows, in disjunctive normal form
+/// with one conjunction of literals per row. Each row holds a value for every variable,
+/// in the order of `vars`, and a row of another length is rejected.
+/// Without any rows the formula is `0`.
+pub fn from_truth_table(vars: &[String], minterms: &[Vec<bool>]) -> Result<ASTNode> {
+    if let Some((i, row)) = minterms
+        .iter()
+        .enumerate()
+        .find(|(_, row)| row.len() != vars.len())
+    {
+        return Err(anyhow!(
+            "Row {} has {} values, expected one for each of the {} variables",
+            i + 1,
+            row.len(),
+            vars.len()
+        ));
+    }
+    let terms = minterms.iter().map(|row| {
+        Implicant {
+            values: row.iter().map(|value| Some(*value)).collect(),
+        }
+        .to_ast(vars)
+    });
+    Ok(join(terms, Operator::Or).unwrap_or_else(|| ASTNode::new(Token::Value(Value::Bool(false)))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::interpreter::{assignments, evaluate_ref};
+
+    use super::from_truth_table;
+
+    fn vars(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_from_truth_table_xor() {
+        let names = vars(&["p", "q"]);
+
+        let root = from_truth_table(&names, &[vec![false, true], vec![true, false]]).unwrap();
+
+        assert_eq!(root.to_expr_string(), "~p ^ q v p ^ ~q");
+        for values in assignments(&names).unwrap() {
+            let expected = values[0] != values[1];
+            let assigned: HashMap<String, bool> = names.iter().cloned().zip(values).collect();
+            assert_eq!(evaluate_ref(&root, &assigned).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_from_truth_table_without_rows() {
+        let root = from_truth_table(&vars(&["p"]), &[]).unwrap();
+
+        assert_eq!(root.to_expr_string(), "0");
+    }
+
+    #[test]
+    fn test_from_truth_table_rejects_row_length() {
+        let error = from_truth_table(&vars(&["p", "q"]), &[vec![true, false], vec![true]]);
+
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Row 2 has 1 values, expected one for each of the 2 variables"
+        );
+    }
+}
@@ -0,0 +1,548 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+
+use crate::interpreter::{assignments, descend, evaluate_ref, EvalOptions};
+use crate::lexer::{Operator, Token, Value};
+use crate::parser::{ASTNode, StoredVariables};
+
+/// Largest number of variables `truth_table` agrees to enumerate
+pub const MAX_TABLE_VARIABLES: usize = 20;
+
+#[derive(Debug, PartialEq)]
+pub struct Row {
+    pub assignment: Vec<(String, bool)>,
+    pub result: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TruthTable {
+    pub variables: Vec<String>,
+    pub rows: Vec<Row>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ComparisonRow {
+    pub assignment: Vec<(String, bool)>,
+    pub left: bool,
+    pub right: bool,
+}
+
+impl ComparisonRow {
+    pub fn matches(&self) -> bool {
+        self.left == self.right
+    }
+}
+
+/// Results of two formulas side by side, over the union of their free variables
+#[derive(Debug, PartialEq)]
+pub struct ComparisonTable {
+    pub left_expr: String,
+    pub right_expr: String,
+    pub variables: Vec<String>,
+    pub rows: Vec<ComparisonRow>,
+}
+
+impl ComparisonTable {
+    pub fn mismatches(&self) -> Vec<&ComparisonRow> {
+        self.rows.iter().filter(|row| !row.matches()).collect()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut columns: Vec<&str> = self.variables.iter().map(|v| v.as_str()).collect();
+        columns.extend([self.left_expr.as_str(), self.right_expr.as_str(), "match"]);
+        let mut markdown = format!(
+            "| {} |\n|{}\n",
+            columns.join(" | "),
+            "---|".repeat(columns.len())
+        );
+        for row in &self.rows {
+            let mut cells: Vec<&str> = row
+                .assignment
+                .iter()
+                .map(|(_, value)| bit(*value))
+                .collect();
+            cells.extend([
+                bit(row.left),
+                bit(row.right),
+                if row.matches() { "yes" } else { "**no**" },
+            ]);
+            markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+        markdown
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableFormat {
+    Csv,
+    Markdown,
+    /// Grid drawn with `+`, `-` and `|`, columns padded to the width of their names
+    Ascii,
+}
+
+impl TruthTable {
+    pub fn write(&self, w: &mut dyn Write, format: TableFormat) -> Result<()> {
+        write_header(w, &self.variables, format)?;
+        for row in &self.rows {
+            let values: Vec<bool> = row.assignment.iter().map(|(_, value)| *value).collect();
+            write_row(w, &self.variables, &values, row.result, format)?;
+        }
+        write_footer(w, &self.variables, format)
+    }
+}
+
+fn evaluate_row(root: &ASTNode, vars: &[String], values: &[bool]) -> Result<bool> {
+    let assigned: StoredVariables = vars.iter().cloned().zip(values.iter().copied()).collect();
+    evaluate_ref(root, &assigned)
+}
+
+/// Truth table over all the variables of the formula, in alphabetical order
+pub fn truth_table(root: &ASTNode) -> Result<TruthTable> {
+    let vars: Vec<String> = root.free_variables().into_iter().collect();
+    if vars.len() > MAX_TABLE_VARIABLES {
+        return Err(anyhow!(
+            "Too many variables for a truth table: {}, at most {} are supported",
+            vars.len(),
+            MAX_TABLE_VARIABLES
+        ));
+    }
+    truth_table_over(root, &vars)
+}
+
+/// Results of `truth_table` in the same row order, computed for 64 rows at once.
+/// Every variable gets a column of bits and the tree is walked only once,
+/// combining whole columns with bitwise operators.
+pub fn truth_table_fast(root: &ASTNode) -> Result<Vec<bool>> {
+    truth_table_fast_with(root, &EvalOptions::default())
+}
+
+/// Same as `truth_table_fast`, with trees deeper than the `max_depth` of the options rejected
+pub fn truth_table_fast_with(root: &ASTNode, options: &EvalOptions) -> Result<Vec<bool>> {
+    let vars: Vec<String> = root.free_variables().into_iter().collect();
+    if vars.len() > MAX_TABLE_VARIABLES {
+        return Err(anyhow!(
+            "Too many variables for a truth table: {}, at most {} are supported",
+            vars.len(),
+            MAX_TABLE_VARIABLES
+        ));
+    }
+    let rows = 1usize << vars.len();
+    let columns = eval_columns(root, &vars, rows.div_ceil(64), options.max_depth)?;
+    Ok((0..rows)
+        .map(|row| (columns[row / 64] >> (row % 64)) & 1 == 1)
+        .collect())
+}
+
+/// Column of the variable at `index`, the first variable being the slowest changing one
+fn variable_column(index: usize, count: usize, words: usize) -> Vec<u64> {
+    let shift = count - 1 - index;
+    (0..words)
+        .map(|word| {
+            (0..64).fold(0, |bits, bit| {
+                let row = word * 64 + bit;
+                bits | ((row >> shift) as u64 & 1) << bit
+            })
+        })
+        .collect()
+}
+
+/// Columns of the node, with `depth` levels left for the node and its operands
+fn eval_columns(node: &ASTNode, vars: &[String], words: usize, depth: usize) -> Result<Vec<u64>> {
+    let depth = descend(depth)?;
+    let operand = |child: &Option<Box<ASTNode>>| match child.as_deref() {
+        Some(child) => eval_columns(child, vars, words, depth),
+        None => Err(anyhow!("Operator {:?} is missing an operand", node.token)),
+    };
+    match &node.token {
+        Token::Value(Value::Bool(value)) => Ok(vec![if *value { u64::MAX } else { 0 }; words]),
+        Token::Value(Value::Variable(var)) => match vars.iter().position(|v| v == var) {
+            Some(index) => Ok(variable_column(index, vars.len(), words)),
+            None => Err(anyhow!("Undefined variable {}", var)),
+        },
+        Token::Operator(Operator::Not) => Ok(operand(&node.left)?.iter().map(|a| !a).collect()),
+        Token::Operator(op) => {
+            let func: fn(u64, u64) -> u64 = match op {
+                Operator::Equivalence => |a, b| !(a ^ b),
+                Operator::Implication => |a, b| !a | b,
+                Operator::Or => |a, b| a | b,
+                Operator::Nor => |a, b| !(a | b),
+                Operator::Xor => |a, b| a ^ b,
+                Operator::And => |a, b| a & b,
+                Operator::Nand => |a, b| !(a & b),
+                other => return Err(anyhow!("Unexpected operator {:?}", other)),
+            };
+            let left = operand(&node.left)?;
+            let right = operand(&node.right)?;
+            Ok(left.iter().zip(&right).map(|(a, b)| func(*a, *b)).collect())
+        }
+    }
+}
+
+/// Truth table of `truth_table` in the CSV format, with a header of the variables
+/// and `result`. A formula without variables has a lone `result` column and a single row.
+pub fn truth_table_csv(root: &ASTNode) -> Result<String> {
+    let mut csv = vec![];
+    truth_table(root)?.write(&mut csv, TableFormat::Csv)?;
+    Ok(String::from_utf8(csv)?)
+}
+
+pub fn truth_table_over(root: &ASTNode, vars: &[String]) -> Result<TruthTable> {
+    let mut rows = vec![];
+    for values in assignments(vars)? {
+        let result = evaluate_row(root, vars, &values)?;
+        let assignment = vars.iter().cloned().zip(values).collect();
+        rows.push(Row { assignment, result });
+    }
+    Ok(TruthTable {
+        variables: vars.to_vec(),
+        rows,
+    })
+}
+
+/// Results of the whole truth table in a single line, e.g. `0110` for `p + q` over `[p, q]`
+pub fn truth_vector_string(root: &ASTNode, vars: &[String]) -> Result<String> {
+    let mut vector = String::new();
+    for values in assignments(vars)? {
+        vector.push_str(bit(evaluate_row(root, vars, &values)?));
+    }
+    Ok(vector)
+}
+
+/// Writes every row as soon as it's evaluated, without keeping the table in memory
+pub fn write_truth_table_streaming(
+    root: &ASTNode,
+    vars: &[String],
+    w: &mut dyn Write,
+    format: TableFormat,
+) -> Result<()> {
+    write_header(w, vars, format)?;
+    for values in assignments(vars)? {
+        let result = evaluate_row(root, vars, &values)?;
+        write_row(w, vars, &values, result, format)?;
+    }
+    write_footer(w, vars, format)
+}
+
+pub fn compare_tables(a: &ASTNode, b: &ASTNode) -> Result<ComparisonTable> {
+    let mut variables = a.free_variables();
+    variables.extend(b.free_variables());
+    let variables: Vec<String> = variables.into_iter().collect();
+
+    let mut rows = vec![];
+    for values in assignments(&variables)? {
+        let left = evaluate_row(a, &variables, &values)?;
+        let right = evaluate_row(b, &variables, &values)?;
+        let assignment = variables.iter().cloned().zip(values).collect();
+        rows.push(ComparisonRow {
+            assignment,
+            left,
+            right,
+        });
+    }
+    Ok(ComparisonTable {
+        left_expr: a.to_expr_string(),
+        right_expr: b.to_expr_string(),
+        variables,
+        rows,
+    })
+}
+
+fn columns(vars: &[String]) -> Vec<&str> {
+    let mut columns: Vec<&str> = vars.iter().map(|v| v.as_str()).collect();
+    columns.push("result");
+    columns
+}
+
+fn ascii_border(columns: &[&str]) -> String {
+    let dashes: Vec<String> = columns.iter().map(|c| "-".repeat(c.len() + 2)).collect();
+    format!("+{}+", dashes.join("+"))
+}
+
+fn write_header(w: &mut dyn Write, vars: &[String], format: TableFormat) -> Result<()> {
+    let columns = columns(vars);
+    match format {
+        TableFormat::Csv => writeln!(w, "{}", columns.join(","))?,
+        TableFormat::Markdown => {
+            writeln!(w, "| {} |", columns.join(" | "))?;
+            writeln!(w, "|{}", "---|".repeat(columns.len()))?;
+        }
+        TableFormat::Ascii => {
+            writeln!(w, "{}", ascii_border(&columns))?;
+            writeln!(w, "| {} |", columns.join(" | "))?;
+            writeln!(w, "{}", ascii_border(&columns))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_row(
+    w: &mut dyn Write,
+    vars: &[String],
+    values: &[bool],
+    result: bool,
+    format: TableFormat,
+) -> Result<()> {
+    let mut cells: Vec<&str> = values.iter().map(|v| bit(*v)).collect();
+    cells.push(bit(result));
+    match format {
+        TableFormat::Csv => writeln!(w, "{}", cells.join(","))?,
+        TableFormat::Markdown => writeln!(w, "| {} |", cells.join(" | "))?,
+        TableFormat::Ascii => {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(columns(vars))
+                .map(|(cell, column)| format!("{:<width$}", cell, width = column.len()))
+                .collect();
+            writeln!(w, "| {} |", padded.join(" | "))?
+        }
+    }
+    Ok(())
+}
+
+fn write_footer(w: &mut dyn Write, vars: &[String], format: TableFormat) -> Result<()> {
+    if format == TableFormat::Ascii {
+        writeln!(w, "{}", ascii_border(&columns(vars)))?;
+    }
+    Ok(())
+}
+
+fn bit(value: bool) -> &'static str {
+    if value {
+        "1"
+    } else {
+        "0"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::interpreter::EvalOptions;
+    use crate::parser::parse;
+
+    use super::{
+        compare_tables, truth_table, truth_table_csv, truth_table_fast, truth_table_fast_with,
+        truth_table_over, truth_vector_string, write_truth_table_streaming, TableFormat,
+    };
+
+    fn vars(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_truth_table_over_row_order() {
+        let root = parse("p ^ ~q");
+        let table = truth_table_over(&root, &vars(&["p", "q"])).unwrap();
+
+        let results: Vec<bool> = table.rows.iter().map(|row| row.result).collect();
+
+        assert_eq!(results, vec![false, false, true, false]);
+        assert_eq!(
+            table.rows[2].assignment,
+            vec![("p".to_string(), true), ("q".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn test_truth_table_csv() {
+        let csv = truth_table_csv(&parse("q ^ p")).unwrap();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            vec!["p,q,result", "0,0,0", "0,1,0", "1,0,0", "1,1,1"]
+        );
+        assert_eq!(truth_table_csv(&parse("1 ^ ~0")).unwrap(), "result\n1\n");
+    }
+
+    #[rstest]
+    #[case(TableFormat::Csv)]
+    #[case(TableFormat::Markdown)]
+    #[case(TableFormat::Ascii)]
+    fn test_streaming_matches_materialized(#[case] format: TableFormat) {
+        let root = parse("(p => q) v r");
+        let names = vars(&["p", "q", "r"]);
+        let mut streamed: Vec<u8> = vec![];
+        let mut materialized: Vec<u8> = vec![];
+
+        write_truth_table_streaming(&root, &names, &mut streamed, format).unwrap();
+        truth_table_over(&root, &names)
+            .unwrap()
+            .write(&mut materialized, format)
+            .unwrap();
+
+        assert_eq!(streamed, materialized);
+        assert_eq!(
+            String::from_utf8(streamed).unwrap().lines().count(),
+            9 + format_extra_lines(format)
+        );
+    }
+
+    fn format_extra_lines(format: TableFormat) -> usize {
+        match format {
+            TableFormat::Csv => 0,
+            TableFormat::Markdown => 1,
+            TableFormat::Ascii => 3,
+        }
+    }
+
+    #[rstest]
+    #[case("p ^ ~q")]
+    #[case("1 => 0")]
+    #[case("(p nand q) <=> ~r nor s")]
+    #[case("a + b + c + d + e + f + g => h ^ ~a")]
+    fn test_truth_table_fast_matches_naive(#[case] expr: &str) {
+        let root = parse(expr);
+        let naive: Vec<bool> = truth_table(&root)
+            .unwrap()
+            .rows
+            .iter()
+            .map(|row| row.result)
+            .collect();
+
+        assert_eq!(truth_table_fast(&root).unwrap(), naive);
+    }
+
+    #[test]
+    fn test_truth_table_sorts_variables() {
+        let table = truth_table(&parse("r v (q ^ p)")).unwrap();
+
+        assert_eq!(table.variables, vars(&["p", "q", "r"]));
+        assert_eq!(table.rows.len(), 8);
+    }
+
+    #[test]
+    fn test_truth_table_without_variables() {
+        let table = truth_table(&parse("1 => 0")).unwrap();
+
+        assert!(table.variables.is_empty());
+        assert_eq!(table.rows.len(), 1);
+        assert!(table.rows[0].assignment.is_empty());
+        assert!(!table.rows[0].result);
+    }
+
+    #[test]
+    fn test_truth_table_should_fail_on_too_many_variables() {
+        let names: Vec<String> = ('a'..='u').map(|c| c.to_string()).collect();
+        let root = parse(&names.join(" ^ "));
+
+        let result = truth_table(&root);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_ascii_table_output() {
+        let root = parse("p + q");
+        let mut out: Vec<u8> = vec![];
+
+        truth_table(&root)
+            .unwrap()
+            .write(&mut out, TableFormat::Ascii)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "+---+---+--------+
+| p | q | result |
++---+---+--------+
+| 0 | 0 | 0      |
+| 0 | 1 | 1      |
+| 1 | 0 | 1      |
+| 1 | 1 | 0      |
++---+---+--------+
+"
+        );
+    }
+
+    #[test]
+    fn test_streaming_csv_output() {
+        let root = parse("p ^ q");
+        let mut out: Vec<u8> = vec![];
+
+        write_truth_table_streaming(&root, &vars(&["p", "q"]), &mut out, TableFormat::Csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "p,q,result\n0,0,0\n0,1,0\n1,0,0\n1,1,1\n"
+        );
+    }
+
+    #[test]
+    fn test_streaming_should_fail_on_unlisted_variable() {
+        let root = parse("p ^ q");
+        let mut out: Vec<u8> = vec![];
+
+        let result = write_truth_table_streaming(&root, &vars(&["p"]), &mut out, TableFormat::Csv);
+
+        assert!(result.is_err());
+    }
+
+    #[rstest]
+    #[case("p + q", "0110")]
+    #[case("p ^ q", "0001")]
+    #[case("p => q", "1101")]
+    fn test_truth_vector_string(#[case] expr: &str, #[case] expected: &str) {
+        let vars = vec!["p".to_string(), "q".to_string()];
+
+        let result = truth_vector_string(&parse(expr), &vars).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_compare_tables_mismatching_rows() {
+        let comparison = compare_tables(&parse("p => q"), &parse("p ^ q")).unwrap();
+
+        let mismatches: Vec<_> = comparison
+            .mismatches()
+            .iter()
+            .map(|row| row.assignment.clone())
+            .collect();
+
+        assert_eq!(
+            mismatches,
+            vec![
+                vec![("p".to_string(), false), ("q".to_string(), false)],
+                vec![("p".to_string(), false), ("q".to_string(), true)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_tables_markdown() {
+        let comparison = compare_tables(&parse("p => q"), &parse("p ^ q")).unwrap();
+
+        assert_eq!(
+            comparison.to_markdown(),
+            "| p | q | p => q | p ^ q | match |\n\
+             |---|---|---|---|---|\n\
+             | 0 | 0 | 1 | 0 | **no** |\n\
+             | 0 | 1 | 1 | 0 | **no** |\n\
+             | 1 | 0 | 0 | 0 | yes |\n\
+             | 1 | 1 | 1 | 1 | yes |\n"
+        );
+    }
+
+    #[test]
+    fn test_compare_tables_union_of_variables() {
+        let comparison = compare_tables(&parse("p"), &parse("q v ~q")).unwrap();
+
+        assert_eq!(comparison.variables, vec!["p", "q"]);
+        assert_eq!(comparison.mismatches().len(), 2);
+    }
+
+    #[test]
+    fn test_truth_table_fast_depth_limit() {
+        let root = parse("~(p ^ q)");
+        let within = |max_depth| truth_table_fast_with(&root, &EvalOptions { max_depth });
+
+        assert_eq!(
+            within(2).unwrap_err().to_string(),
+            "Expression too deeply nested"
+        );
+        assert_eq!(within(3).unwrap(), vec![true, true, true, false]);
+    }
+}
@@ -0,0 +1,29 @@
+use wasm_bindgen::prelude::*;
+
+/// Entry point for the browser, evaluating the statement like `eval_str`.
+/// The error is the message of the parsing or evaluation failure.
+#[wasm_bindgen]
+pub fn evaluate_expression(expr: &str) -> Result<JsValue, JsValue> {
+    crate::eval_str(expr)
+        .map(JsValue::from_bool)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::evaluate_expression;
+
+    #[wasm_bindgen_test]
+    fn test_evaluate_expression() {
+        assert_eq!(
+            evaluate_expression("p := 1 p ^ 0 v 1").unwrap().as_bool(),
+            Some(true)
+        );
+        assert_eq!(
+            evaluate_expression("1 ^").unwrap_err().as_string().unwrap(),
+            "Operator 'And' missing right operand"
+        );
+    }
+}
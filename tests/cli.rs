@@ -0,0 +1,132 @@
+use std::fs;
+use std::process::{self, Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_logic-solver"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_check_tautology_exits_successfully() {
+    let output = run(&["check", "-e", "p v ~p"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Classification: tautology\n"
+    );
+}
+
+#[test]
+fn test_check_contingent_fails() {
+    let output = run(&["check", "-e", "p => q"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Classification: contingent\n"
+    );
+}
+
+#[test]
+fn test_check_evaluates_without_free_variables() {
+    let output = run(&["check", "-e", "1 ^ 0"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Result: false\n");
+}
+
+#[test]
+fn test_exit_code_follows_result() {
+    let true_output = run(&["--exit-code", "-e", "1 v 0"]);
+    let false_output = run(&["--exit-code", "-e", "1 ^ 0"]);
+
+    assert_eq!(true_output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&true_output.stdout),
+        "Result: true\n"
+    );
+    assert_eq!(false_output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&false_output.stdout),
+        "Result: false\n"
+    );
+}
+
+#[test]
+fn test_quiet_prints_only_result_bit() {
+    let output = run(&["--quiet", "--trace", "-e", "p := 1 p => 0"]);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "0\n");
+}
+
+#[test]
+fn test_result_options_need_a_single_result() {
+    let unassigned = run(&["--trace", "-e", "p ^ q"]);
+    let table = run(&["--table", "--quiet", "--exit-code", "-e", "p ^ q"]);
+    let several = run(&["--graph", "graph.dot", "-e", "1; 0"]);
+    let check = run(&["check", "--quiet", "-e", "p v ~p"]);
+
+    assert!(String::from_utf8_lossy(&unassigned.stderr)
+        .contains("Can't use --trace without a single result, assign all the variables"));
+    assert!(String::from_utf8_lossy(&table.stderr)
+        .contains("Can't use --quiet, --exit-code with --table, it prints no result"));
+    assert!(String::from_utf8_lossy(&several.stderr)
+        .contains("Only the results are printed for several statements"));
+    assert!(String::from_utf8_lossy(&check.stderr)
+        .contains("Subcommand check takes a single statement and no other options"));
+    for output in [unassigned, table, several, check] {
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+}
+
+#[test]
+fn test_tables_are_over_unassigned_variables() {
+    let table = run(&["--table", "-e", "p := 1 p ^ q"]);
+    let csv = run(&["--csv", "-e", "p := 1 p ^ q"]);
+
+    assert!(table.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&table.stdout),
+        "+---+--------+\n| q | result |\n+---+--------+\n| 0 | 0      |\n| 1 | 1      |\n+---+--------+\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&csv.stdout), "q,result\n0,0\n1,1\n");
+}
+
+#[test]
+fn test_every_output_is_over_unassigned_variables() {
+    let latex = run(&["--latex", "-e", "p := 1 p ^ ~q"]);
+    let tree = run(&["--tree", "-e", "p := 1 p ^ ~q"]);
+    let stats = run(&["--stats", "-e", "p := 1 p ^ ~q"]);
+
+    assert_eq!(String::from_utf8_lossy(&latex.stdout), "\\neg q\n");
+    assert_eq!(String::from_utf8_lossy(&tree.stdout), "Not\n└─ q\n");
+    assert_eq!(
+        String::from_utf8_lossy(&stats.stdout),
+        "Depth: 2\nNodes: 2\nOperators: 1\n"
+    );
+}
+
+#[test]
+fn test_assign_overrides_file_assignments() {
+    let path = std::env::temp_dir().join(format!("logic-solver-assign-{}.prop", process::id()));
+    fs::write(&path, "p := 1\nq := 1\np ^ ~q\n").unwrap();
+
+    let output = run(&[path.to_str().unwrap(), "--assign", "q=0"]);
+    fs::remove_file(&path).unwrap();
+    let derived = run(&["-e", "p := 1 q := p q", "--assign", "p=0"]);
+    let free = run(&["-e", "p => q", "--assign", "p=1", "--assign", "q=0"]);
+    let malformed = run(&["-e", "p", "--assign", "p=2"]);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Result: true\n");
+    assert_eq!(String::from_utf8_lossy(&derived.stdout), "Result: false\n");
+    assert_eq!(String::from_utf8_lossy(&free.stdout), "Result: false\n");
+    assert!(!malformed.status.success());
+    assert!(String::from_utf8_lossy(&malformed.stderr)
+        .contains("Invalid value '2' in --assign p=2, expected 1, 0, true or false"));
+}
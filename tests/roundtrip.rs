@@ -0,0 +1,83 @@
+use proptest::prelude::*;
+
+use logic_solver::interpreter::evaluate_ref;
+use logic_solver::lexer::{Operator, Token, Value};
+use logic_solver::parser::{combine, negate_formula, ASTNode};
+use logic_solver::{parse_expr, stored_variables};
+
+const VARIABLES: [&str; 3] = ["p", "q", "r"];
+
+const BINARY: [Operator; 7] = [
+    Operator::Equivalence,
+    Operator::Implication,
+    Operator::Or,
+    Operator::Nor,
+    Operator::Xor,
+    Operator::And,
+    Operator::Nand,
+];
+
+fn leaf() -> impl Strategy<Value = ASTNode> {
+    prop_oneof![
+        any::<bool>().prop_map(|b| ASTNode::new(Token::Value(Value::Bool(b)))),
+        prop::sample::select(&VARIABLES[..])
+            .prop_map(|var| ASTNode::new(Token::Value(Value::Variable(var.to_string())))),
+    ]
+}
+
+/// Random trees built directly, without going through the parser
+fn formula() -> impl Strategy<Value = ASTNode> {
+    leaf().prop_recursive(6, 64, 2, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(negate_formula),
+            (inner.clone(), prop::sample::select(&BINARY[..]), inner)
+                .prop_map(|(left, op, right)| combine(left, op, right).unwrap()),
+        ]
+    })
+}
+
+/// Evaluates the tree straight from the definitions of the connectives
+fn reference_eval(node: &ASTNode, assignment: &[bool; 3]) -> bool {
+    let operand =
+        |child: &Option<Box<ASTNode>>| reference_eval(child.as_ref().unwrap(), assignment);
+    match &node.token {
+        Token::Value(Value::Bool(b)) => *b,
+        Token::Value(Value::Variable(name)) => {
+            assignment[VARIABLES.iter().position(|var| var == name).unwrap()]
+        }
+        Token::Operator(Operator::Not) => !operand(&node.left),
+        Token::Operator(op) => {
+            let (left, right) = (operand(&node.left), operand(&node.right));
+            match op {
+                Operator::Equivalence => left == right,
+                Operator::Implication => !left || right,
+                Operator::Or => left || right,
+                Operator::Nor => !(left || right),
+                Operator::Xor => left != right,
+                Operator::And => left && right,
+                Operator::Nand => !(left && right),
+                other => panic!("Unexpected operator {:?} in a generated formula", other),
+            }
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_rendered_formula_parses_back_to_same_function(root in formula()) {
+        let expr = root.to_expr_string();
+        let (parsed, _) = parse_expr(&expr).unwrap();
+
+        for bits in 0..8u8 {
+            let assignment = [bits & 4 != 0, bits & 2 != 0, bits & 1 != 0];
+            let vars = stored_variables(VARIABLES.iter().copied().zip(assignment));
+            prop_assert_eq!(
+                evaluate_ref(&parsed, &vars).unwrap(),
+                reference_eval(&root, &assignment),
+                "{} under {:?}",
+                expr,
+                assignment
+            );
+        }
+    }
+}